@@ -0,0 +1,61 @@
+use cgmath::Vector2;
+use creek::{GlobalEvent, CreekAction, actors::ActorTypes};
+use rue::{
+    core::{Game, GameHooks, GameInfo},
+    renderer::{RenderContext, RenderableInstance, InterpolatedInstance},
+    math::Transform2D,
+    helpers::colors::Color,
+};
+
+#[derive(Clone)]
+enum DemoActors {}
+
+impl ActorTypes for DemoActors {
+    fn propogate_global_event(&mut self, _event:&GlobalEvent) -> Option<&Vec<CreekAction>> {
+        None
+    }
+}
+
+// Bounces the red channel of the clear colour back and forth behind a single quad.
+struct ClearColorDemo {
+    red: f64,
+    dir: f64,
+    quad: InterpolatedInstance,
+}
+
+impl GameHooks<DemoActors> for ClearColorDemo {
+    fn on_fixed_update(&mut self, _game: &mut Game<DemoActors>, delta_time: f32) {
+        if self.red > 1.0 {
+            self.dir = -1.0;
+        }
+        else if self.red < 0.0 {
+            self.dir = 1.0;
+        }
+        self.red += (delta_time as f64) * self.dir;
+
+        let mut next = self.quad.current;
+        next.transform.translation.x = (self.red as f32) - 0.5;
+        self.quad.update(next);
+    }
+
+    fn on_render(&mut self, ctx: &mut RenderContext) {
+        let mut clear_color = ctx.clear_color();
+        clear_color.r = self.red;
+        ctx.set_clear_color(clear_color);
+        // The quad moves in [-0.5, 0.5], zoom in so that covers a good part of the window.
+        ctx.renderer.camera.zoom = 200.0;
+        ctx.draw_interpolated(&self.quad);
+        // The track the quad slides along.
+        ctx.shapes().line(Vector2::new(-0.5, 0.0), Vector2::new(0.5, 0.0), 0.01, Color::rgba(1.0, 1.0, 1.0, 0.5));
+    }
+}
+
+fn main() {
+    Game::<DemoActors>::new(GameInfo::default())
+        .with_hooks(ClearColorDemo {
+            red: 1.0,
+            dir: 1.0,
+            quad: InterpolatedInstance::new(RenderableInstance::from(Transform2D::new(Vector2::new(0.0, 0.0)))),
+        })
+        .run();
+}
@@ -1,2 +1,234 @@
-use crate::renderer;
+use cgmath::{Matrix3, Matrix4, Vector2};
+use creek::{CreekAction, actors::{Actor, ActorID}};
 
+use crate::helpers::colors::Color;
+use crate::math::{Rect, Transform2D, affine_to_matrix4};
+use crate::renderer::{Renderable, RenderData, Renderer, RenderableInstanceRaw, texture::TextureHandle, blend::BlendMode, material::MaterialHandle, mesh::MeshHandle, batch::{BatchKey, DrawOrder, RenderLayer}};
+
+pub enum SpriteEvent {
+    Move(Vector2<f32>),
+    SetPosition(Vector2<f32>),
+    SetRotation(f32),
+    SetTexture(TextureHandle, Option<Rect>),
+    SetTint(Color),
+    SetBlend(BlendMode),
+    SetMaterial(MaterialHandle),
+    SetParent(Option<Matrix3<f32>>),
+    Flip { x: bool, y: bool },
+}
+
+#[derive(Clone, Debug)]
+pub struct Sprite {
+    pub texture: TextureHandle,
+    // Region of the texture to draw in pixels, None draws the whole texture.
+    pub source_rect: Option<Rect>,
+    pub position: Vector2<f32>,
+    // Counter-clockwise, in radians.
+    pub rotation: f32,
+    pub size: Vector2<f32>,
+    // Point of the sprite that sits on `position` and is rotated around, (0, 0) is the top left corner and (1, 1) the bottom right.
+    pub origin: Vector2<f32>,
+    pub tint: Color,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    pub layer: RenderLayer,
+    // Order within the layer, higher values are drawn on top.
+    pub z: f32,
+    pub blend: BlendMode,
+    pub material: MaterialHandle,
+    // World matrix of whatever the sprite is attached to, position and rotation are relative to it.
+    pub parent: Option<Matrix3<f32>>,
+    id: Option<ActorID>,
+    actions: Vec<CreekAction>,
+}
+
+impl Sprite {
+    pub fn new(texture: TextureHandle, size: Vector2<f32>) -> Self {
+        Sprite {
+            texture,
+            source_rect: None,
+            position: Vector2::new(0.0, 0.0),
+            rotation: 0.0,
+            size,
+            origin: Vector2::new(0.5, 0.5),
+            tint: Color::default(),
+            flip_x: false,
+            flip_y: false,
+            layer: RenderLayer::default(),
+            z: 0.0,
+            blend: BlendMode::default(),
+            material: MaterialHandle::default(),
+            parent: None,
+            id: None,
+            actions: Vec::new(),
+        }
+    }
+
+    pub fn with_position(mut self, position: Vector2<f32>) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_source_rect(mut self, source_rect: Rect) -> Self {
+        self.source_rect = Some(source_rect);
+        self
+    }
+
+    pub fn with_layer(mut self, layer: RenderLayer) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    pub fn with_z(mut self, z: f32) -> Self {
+        self.z = z;
+        self
+    }
+
+    pub fn with_origin(mut self, origin: Vector2<f32>) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    pub fn with_blend(mut self, blend: BlendMode) -> Self {
+        self.blend = blend;
+        self
+    }
+
+    pub fn with_material(mut self, material: MaterialHandle) -> Self {
+        self.material = material;
+        self
+    }
+
+    pub fn with_parent(mut self, parent: Matrix3<f32>) -> Self {
+        self.parent = Some(parent);
+        self
+    }
+
+    pub fn with_tint(mut self, tint: Color) -> Self {
+        self.tint = tint;
+        self
+    }
+
+    pub fn flipped(mut self, flip_x: bool, flip_y: bool) -> Self {
+        self.flip_x = flip_x;
+        self.flip_y = flip_y;
+        self
+    }
+
+    // Source rect in normalised texture coordinates, with flipping applied by mirroring the rect.
+    pub fn uv_rect(&self, texture_size: (u32, u32)) -> [f32; 4] {
+        let (width, height) = (texture_size.0 as f32, texture_size.1 as f32);
+        let mut uv = match self.source_rect {
+            Some(rect) => [rect.x / width, rect.y / height, rect.width / width, rect.height / height],
+            None => RenderableInstanceRaw::FULL_UV_RECT,
+        };
+        if self.flip_x {
+            uv[0] += uv[2];
+            uv[2] = -uv[2];
+        }
+        if self.flip_y {
+            uv[1] += uv[3];
+            uv[3] = -uv[3];
+        }
+        uv
+    }
+
+    pub fn transform(&self) -> Transform2D {
+        // The unit quad is centred on (0, 0) with y pointing up, so the origin is moved onto (0, 0) before scaling.
+        Transform2D::new(self.position)
+            .with_rotation(self.rotation)
+            .with_scale(self.size)
+            .with_pivot(Vector2::new(self.origin.x - 0.5, 0.5 - self.origin.y))
+    }
+
+    // Matrix to pass as the parent of sprites attached to this one. Unlike the model matrix it is not scaled by the size.
+    pub fn world_matrix(&self) -> Matrix3<f32> {
+        let local = Transform2D::new(self.position).with_rotation(self.rotation).matrix();
+        match self.parent {
+            Some(parent) => parent * local,
+            None => local,
+        }
+    }
+
+    pub fn model_matrix(&self) -> Matrix4<f32> {
+        let local = self.transform().matrix();
+        affine_to_matrix4(&match self.parent {
+            Some(parent) => parent * local,
+            None => local,
+        })
+    }
+
+    pub fn batch_key(&self) -> BatchKey {
+        BatchKey {
+            material: self.material,
+            blend: self.blend,
+            mesh: MeshHandle::QUAD,
+            texture: self.texture,
+        }
+    }
+
+    pub fn draw_order(&self) -> DrawOrder {
+        DrawOrder {
+            layer: self.layer,
+            z: self.z,
+            // Where the sprite ends up, so children sort among everything else on their layer.
+            y: self.world_matrix().z.y,
+        }
+    }
+
+    pub fn to_raw(&self, texture_size: (u32, u32)) -> RenderableInstanceRaw {
+        RenderableInstanceRaw {
+            model: self.model_matrix().into(),
+            uv_rect: self.uv_rect(texture_size),
+            tint: [self.tint.r as f32, self.tint.g as f32, self.tint.b as f32, self.tint.a as f32],
+        }
+    }
+}
+
+impl Renderable for Sprite {
+    fn prepare_for_render(&self, renderer: &mut Renderer) {
+        let texture_size = renderer.texture_size(self.texture);
+        renderer.submit(RenderData {
+            key: self.batch_key(),
+            order: self.draw_order(),
+            instance: self.to_raw(texture_size),
+        });
+    }
+}
+
+impl Actor for Sprite {
+    type Event = SpriteEvent;
+
+    fn receive_event(&mut self, event:Self::Event) {
+        match event {
+            SpriteEvent::Move(offset) => self.position += offset,
+            SpriteEvent::SetPosition(position) => self.position = position,
+            SpriteEvent::SetRotation(rotation) => self.rotation = rotation,
+            SpriteEvent::SetTexture(texture, source_rect) => {
+                self.texture = texture;
+                self.source_rect = source_rect;
+            },
+            SpriteEvent::SetTint(tint) => self.tint = tint,
+            SpriteEvent::SetBlend(blend) => self.blend = blend,
+            SpriteEvent::SetMaterial(material) => self.material = material,
+            SpriteEvent::SetParent(parent) => self.parent = parent,
+            SpriteEvent::Flip { x, y } => {
+                self.flip_x = x;
+                self.flip_y = y;
+            },
+        }
+    }
+
+    fn get_creek_actions(&self) -> &Vec<CreekAction> {
+        &self.actions
+    }
+
+    fn get_id(&self) -> Option<ActorID> {
+        self.id
+    }
+}
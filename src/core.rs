@@ -1,15 +1,17 @@
-use cgmath::{Quaternion, Vector2};
 use creek::{*, actors::{ActorTypes, ActorHandle, Actor, ActorID}};
 use std::{
-    sync::{Arc}, cell::{RefMut, RefCell}, rc::Rc,
+    sync::{Arc}, cell::{Ref, RefMut, RefCell}, rc::Rc, path::PathBuf,
 };
 use winit::{
-    event::{Event, WindowEvent},
+    event::{Event, WindowEvent, KeyboardInput, ElementState, VirtualKeyCode},
     event_loop::{ControlFlow, EventLoop},
     window::{Window, WindowBuilder}, dpi::{LogicalSize, PhysicalSize},
 };
 
-use crate::renderer::{Renderer, RenderConfig, RenderableInstance};
+use crate::renderer::{Renderer, RenderConfig, RenderContext};
+use crate::time::{FramePacing, FrameLimiter, Clock, SystemClock, Time, TimeHandle};
+use crate::input::{Input, InputHandle, ActionMap};
+use crate::error::RueError;
 
 
 #[derive(Debug)]
@@ -40,38 +42,157 @@ pub struct GameVersion(pub u8, pub u8, pub u8);
 #[derive(Debug)]
 pub struct GameInfo {
     pub name: String,
+    // Fixed updates per second, and the render cap unless frame_pacing says otherwise.
     pub target_fps: u8,
+    pub frame_pacing: Option<FramePacing>,
     pub version: GameVersion,
     pub window_config: WindowConfig,
 }
 
+impl GameInfo {
+    pub fn frame_pacing(&self) -> FramePacing {
+        self.frame_pacing.unwrap_or(FramePacing::Capped(self.target_fps as u32))
+    }
+}
+
 impl Default for GameInfo {
     fn default() -> Self {
         Self {
             name: String::from("My Rue Game"),
             target_fps: 60,
+            frame_pacing: None,
             version: GameVersion(0, 1, 0),
             window_config: WindowConfig::build((640, 480))
         }
     }
 }
 
-pub enum SceneEvent {
+pub enum SceneEvent<T: ActorTypes + Clone> {
+    Push(Creek<T>),
+    // The base scene is never popped, Replace it instead.
+    Pop,
+    Replace(Creek<T>),
+    // Pops everything above the base scene.
+    Clear,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SceneNotification {
+    Enter,
+    Exit,
+    Pause,
+    Resume,
+}
+
+pub type SceneListener<T> = Rc<dyn Fn(&mut Creek<T>, SceneNotification)>;
 
+// Tells scenes apart while notifications for them are queued, stack positions change as scenes come and go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SceneId(u64);
+
+// Notifications waiting for Game to pass them on to GameHooks::on_scene_notification.
+#[derive(Clone)]
+enum PendingNotification<T: ActorTypes + Clone> {
+    Scene(SceneId, SceneNotification),
+    // Kept alive until its Exit notification has been delivered.
+    Exited(SceneId, Creek<T>),
 }
 
 #[derive(Clone)]
 pub struct SceneManager<T: ActorTypes + Clone> {
-    pub cur_scene: Creek<T>,
+    scenes: Vec<(SceneId, Creek<T>)>,
+    next_id: u64,
+    listener: Option<SceneListener<T>>,
+    pending: Vec<PendingNotification<T>>,
     id: Option<ActorID>,
     actions: Vec<CreekAction>
 }
 
+impl<T: ActorTypes + Clone> SceneManager<T> {
+    pub fn new(base_scene: Creek<T>) -> Self {
+        SceneManager {
+            scenes: vec![(SceneId(0), base_scene)],
+            next_id: 1,
+            listener: None,
+            pending: Vec::new(),
+            id: None,
+            actions: Vec::new(),
+        }
+    }
+
+    pub fn cur_scene(&mut self) -> Option<&mut Creek<T>> {
+        self.scenes.last_mut().map(|(_, scene)| scene)
+    }
+
+    fn scene_mut(&mut self, id: SceneId) -> Option<&mut Creek<T>> {
+        self.scenes.iter_mut().find(|(scene_id, _)| *scene_id == id).map(|(_, scene)| scene)
+    }
+
+    fn push_scene(&mut self, scene: Creek<T>) {
+        self.scenes.push((SceneId(self.next_id), scene));
+        self.next_id += 1;
+    }
+
+    pub fn scene_count(&self) -> usize {
+        self.scenes.len()
+    }
+
+    pub fn set_listener(&mut self, listener: SceneListener<T>) {
+        self.listener = Some(listener);
+    }
+
+    fn notify_top(&mut self, notification: SceneNotification) {
+        if let Some((id, scene)) = self.scenes.last_mut() {
+            if let Some(listener) = &self.listener {
+                listener(scene, notification);
+            }
+            self.pending.push(PendingNotification::Scene(*id, notification));
+        }
+    }
+
+    fn exit_top(&mut self) {
+        if let Some((id, mut scene)) = self.scenes.pop() {
+            if let Some(listener) = &self.listener {
+                listener(&mut scene, SceneNotification::Exit);
+            }
+            self.pending.push(PendingNotification::Exited(id, scene));
+        }
+    }
+}
+
 impl<T: ActorTypes + Clone> Actor for SceneManager<T> {
-    type Event = SceneEvent;
+    type Event = SceneEvent<T>;
 
     fn receive_event(&mut self, event:Self::Event) {
-        todo!()
+        match event {
+            SceneEvent::Push(scene) => {
+                self.notify_top(SceneNotification::Pause);
+                self.push_scene(scene);
+                self.notify_top(SceneNotification::Enter);
+            },
+            SceneEvent::Pop => {
+                if self.scenes.len() > 1 {
+                    self.exit_top();
+                    self.notify_top(SceneNotification::Resume);
+                }
+                else {
+                    log::warn!("Tried to pop the base scene, replace it instead.");
+                }
+            },
+            SceneEvent::Replace(scene) => {
+                self.exit_top();
+                self.push_scene(scene);
+                self.notify_top(SceneNotification::Enter);
+            },
+            SceneEvent::Clear => {
+                if self.scenes.len() > 1 {
+                    while self.scenes.len() > 1 {
+                        self.exit_top();
+                    }
+                    self.notify_top(SceneNotification::Resume);
+                }
+            },
+        }
     }
 
     fn get_creek_actions(&self) -> &Vec<CreekAction> {
@@ -95,6 +216,26 @@ impl<T: ActorTypes + Clone> creek::actors::ActorTypes for CoreSystems<T> {
     }
 }
 
+// Entry points for game code. Every hook is optional.
+pub trait GameHooks<T: ActorTypes + Clone + 'static> {
+    fn on_start(&mut self, _game: &mut Game<T>) {}
+    fn on_fixed_update(&mut self, _game: &mut Game<T>, _delta_time: f32) {}
+    // Called once per frame after the fixed updates with the clamped and scaled frame time, 0 while paused.
+    // Alpha is the leftover fraction of a fixed step. Time::unscaled_delta still has the real frame time.
+    fn on_update(&mut self, _game: &mut Game<T>, _delta_time: f32, _alpha: f32) {}
+    fn on_render(&mut self, _ctx: &mut RenderContext) {}
+    // Called for every scene that is entered, exited, paused or resumed, in the order it happened.
+    fn on_scene_notification(&mut self, _scene: &mut Creek<T>, _notification: SceneNotification) {}
+    // Called when a long frame had to be clamped and `dropped` seconds of simulation were skipped, scaled by the time scale.
+    fn on_time_dropped(&mut self, _game: &mut Game<T>, _dropped: f32) {}
+    fn on_exit(&mut self, _game: &mut Game<T>) {}
+}
+
+// Stands in until with_hooks is called, so `hooks` is only ever None while a hook is running.
+struct NoHooks;
+
+impl<T: ActorTypes + Clone + 'static> GameHooks<T> for NoHooks {}
+
 pub struct Game<T: ActorTypes + Clone + 'static> {
     pub running: bool,
     pub game_info: GameInfo,
@@ -105,6 +246,20 @@ pub struct Game<T: ActorTypes + Clone + 'static> {
     pub render_config: Option<Rc<RefCell<RenderConfig>>>,
     pub delta_time: f32,
     pub total_delta_time: f32,
+    // Longest frame fed to the accumulator, anything above it is dropped.
+    pub max_frame_time: f32,
+    pub max_substeps: u32,
+    // Total simulation time skipped so far, scaled by the time scale like Time::delta.
+    pub dropped_time: f32,
+    pub input: Input,
+    // Pressing this writes the current frame to screenshot_dir, None disables it.
+    pub screenshot_key: Option<VirtualKeyCode>,
+    pub screenshot_dir: PathBuf,
+    time: TimeHandle,
+    hooks: Option<Box<dyn GameHooks<T>>>,
+    clock: Box<dyn Clock>,
+    last_tick: Option<std::time::Duration>,
+    accumulated_frame_time: f32,
 }
 
 pub struct GameState {
@@ -113,6 +268,7 @@ pub struct GameState {
 
 impl<T: ActorTypes + Clone + 'static > Game<T> {
     pub fn new(game_info: GameInfo) -> Game<T> {
+        let delta_time = 1.0 / (game_info.target_fps.max(1) as f32);
         let mut g = Game {
             running: false,
             game_info,
@@ -121,14 +277,21 @@ impl<T: ActorTypes + Clone + 'static > Game<T> {
             event_loop: None,
             renderer: None,
             render_config: None,
-            delta_time: 0.01,
+            delta_time,
             total_delta_time: 0.0,
+            max_frame_time: 0.25,
+            max_substeps: 8,
+            dropped_time: 0.0,
+            input: Input::new(),
+            screenshot_key: Some(VirtualKeyCode::F12),
+            screenshot_dir: PathBuf::from("screenshots"),
+            time: Rc::new(RefCell::new(Time::new(delta_time))),
+            hooks: Some(Box::new(NoHooks)),
+            clock: Box::new(SystemClock::new()),
+            last_tick: None,
+            accumulated_frame_time: 0.0,
         };
-        g.room_manager = g.core_system.add_actor(CoreSystems::SceneManager(SceneManager {
-            cur_scene: Creek::<T>::new(),
-            id: None,
-            actions: Vec::new(),
-        }));
+        g.room_manager = g.core_system.add_actor(CoreSystems::SceneManager(SceneManager::new(Creek::<T>::new())));
         g
     }
 
@@ -137,34 +300,314 @@ impl<T: ActorTypes + Clone + 'static > Game<T> {
         self
     }
 
+    pub fn with_hooks(mut self, hooks: impl GameHooks<T> + 'static) -> Self {
+        self.hooks = Some(Box::new(hooks));
+        self
+    }
+
+    pub fn with_simulation_rate(mut self, updates_per_second: u32) -> Self {
+        self.delta_time = 1.0 / (updates_per_second.max(1) as f32);
+        self.time.borrow_mut().set_fixed_delta(self.delta_time);
+        self
+    }
+
+    pub fn with_max_frame_time(mut self, max_frame_time: f32) -> Self {
+        self.max_frame_time = max_frame_time;
+        self
+    }
+
+    pub fn with_max_substeps(mut self, max_substeps: u32) -> Self {
+        self.max_substeps = max_substeps.max(1);
+        self
+    }
+
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self.last_tick = None;
+        self
+    }
+
+    pub fn with_frame_pacing(mut self, frame_pacing: FramePacing) -> Self {
+        self.game_info.frame_pacing = Some(frame_pacing);
+        self
+    }
+
+    pub fn with_action_map(mut self, action_map: ActionMap) -> Self {
+        self.input.set_action_map(action_map);
+        self
+    }
+
+    pub fn with_screenshot_key(mut self, screenshot_key: Option<VirtualKeyCode>) -> Self {
+        self.screenshot_key = screenshot_key;
+        self
+    }
+
+    pub fn with_screenshot_dir(mut self, screenshot_dir: impl Into<PathBuf>) -> Self {
+        self.screenshot_dir = screenshot_dir.into();
+        self
+    }
+
+    // Shared input state, refreshed right before every GlobalEventType::Update reaches the current scene.
+    pub fn input_handle(&self) -> InputHandle {
+        self.input.handle()
+    }
+
+    // Shared time resource, hand clones to actors that need to read game time.
+    pub fn time_handle(&self) -> TimeHandle {
+        self.time.clone()
+    }
+
+    pub fn time(&self) -> Ref<'_, Time> {
+        self.time.borrow()
+    }
+
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time.borrow_mut().set_time_scale(time_scale);
+    }
+
+    // Stops fixed updates, rendering and input keep going.
+    pub fn pause(&mut self) {
+        self.time.borrow_mut().pause();
+    }
+
+    pub fn resume(&mut self) {
+        self.time.borrow_mut().resume();
+    }
+
     pub fn scene(&mut self) -> RefMut<Option<CoreSystems<T>>> {
         self.room_manager.borrow_actor_mut()
     }
 
-    pub fn run(mut self) {
+    pub fn cur_scene(&self) -> Option<RefMut<'_, Creek<T>>> {
+        RefMut::filter_map(self.room_manager.borrow_actor_mut(), |s_manager| {
+            match s_manager {
+                Some(CoreSystems::SceneManager(s)) => s.cur_scene(),
+                _ => None,
+            }
+        }).ok()
+    }
+
+    pub fn scene_count(&self) -> usize {
+        match &*self.room_manager.borrow_actor_mut() {
+            Some(CoreSystems::SceneManager(s)) => s.scene_count(),
+            _ => 0,
+        }
+    }
+
+    pub fn send_scene_event(&mut self, event: SceneEvent<T>) {
+        let mut event = Some(event);
+        self.room_manager.edit_actor(|s_manager| {
+            if let (CoreSystems::SceneManager(s), Some(event)) = (s_manager, event.take()) {
+                s.receive_event(event);
+            }
+        });
+        self.deliver_scene_notifications();
+    }
+
+    fn deliver_scene_notifications(&mut self) {
+        // Scene events sent from inside a hook stay queued until call_hooks has put the hooks back.
+        let hooks = match self.hooks.as_mut() {
+            Some(hooks) => hooks,
+            None => return,
+        };
+        let mut pending = match &mut *self.room_manager.borrow_actor_mut() {
+            Some(CoreSystems::SceneManager(s)) => std::mem::take(&mut s.pending),
+            _ => return,
+        };
+        for i in 0..pending.len() {
+            let (current, later) = pending[i..].split_first_mut().expect("index is in range");
+            match current {
+                PendingNotification::Exited(_, scene) => hooks.on_scene_notification(scene, SceneNotification::Exit),
+                PendingNotification::Scene(id, notification) => {
+                    // Events applied through the creek path pile up until the next drain, so a scene may
+                    // already be off the stack again and only live on in a later Exited entry.
+                    let exited = later.iter_mut().find_map(|later| match later {
+                        PendingNotification::Exited(exited_id, scene) if exited_id == id => Some(scene),
+                        _ => None,
+                    });
+                    match exited {
+                        Some(scene) => hooks.on_scene_notification(scene, *notification),
+                        None => {
+                            if let Some(CoreSystems::SceneManager(s)) = &mut *self.room_manager.borrow_actor_mut() {
+                                if let Some(scene) = s.scene_mut(*id) {
+                                    hooks.on_scene_notification(scene, *notification);
+                                }
+                            }
+                        },
+                    }
+                },
+            }
+        }
+    }
+
+    pub fn with_scene_listener(self, listener: impl Fn(&mut Creek<T>, SceneNotification) + 'static) -> Self {
+        let listener: SceneListener<T> = Rc::new(listener);
+        self.room_manager.edit_actor(|s_manager| {
+            if let CoreSystems::SceneManager(s) = s_manager {
+                s.set_listener(listener.clone());
+            }
+        });
+        self
+    }
+
+    // Hooks get mutable access to the game, so they are moved out for the duration of the call.
+    fn call_hooks(&mut self, f: impl FnOnce(&mut dyn GameHooks<T>, &mut Self)) {
+        if let Some(mut hooks) = self.hooks.take() {
+            f(hooks.as_mut(), self);
+            self.hooks = Some(hooks);
+            self.deliver_scene_notifications();
+        }
+    }
+
+    pub fn start(&mut self) {
+        if !self.running {
+            self.running = true;
+            self.call_hooks(|hooks, game| hooks.on_start(game));
+        }
+    }
+
+    pub fn stop(&mut self) {
+        if self.running {
+            self.call_hooks(|hooks, game| hooks.on_exit(game));
+            self.running = false;
+        }
+    }
+
+    // Pushes a single fixed update into the current scene.
+    fn fixed_update(&mut self) {
+        // Scene events can also arrive through the creek event path.
+        self.deliver_scene_notifications();
+        self.input.snapshot();
+        let delta_time = self.delta_time;
+        self.room_manager.edit_actor(|s_manager| {
+            if let Some(scene) = match s_manager {
+                CoreSystems::SceneManager(s) => s.cur_scene(),
+                _ => None,
+            } {
+                scene.push_event(creek::GlobalEventType::Update(delta_time), None);
+                scene.propagate_events();
+            }
+        });
+        self.call_hooks(|hooks, game| hooks.on_fixed_update(game, delta_time));
+        self.total_delta_time += self.delta_time;
+        self.time.borrow_mut().count_fixed_tick();
+    }
+
+    // Fraction of a fixed step left in the accumulator, used to blend between the last two simulation states.
+    pub fn alpha(&self) -> f32 {
+        self.accumulated_frame_time / self.delta_time
+    }
+
+    // Feeds frame_time seconds into the fixed timestep accumulator and returns how many updates were run.
+    // Frames longer than max_frame_time, or needing more than max_substeps updates, drop the excess time
+    // instead of trying to catch up, which would only make the next frame longer still.
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        let clamped_frame_time = frame_time.min(self.max_frame_time);
+        let (scaled_frame_time, mut dropped, paused) = {
+            let mut time = self.time.borrow_mut();
+            let scaled_frame_time = time.begin_frame(frame_time, clamped_frame_time);
+            // In simulation seconds like the substep excess below, so time scale and pausing apply to it as well.
+            let dropped = if time.is_paused() { 0.0 } else { (frame_time - clamped_frame_time) * time.time_scale() };
+            (scaled_frame_time, dropped, time.is_paused())
+        };
+        if paused {
+            // No fixed updates will take the snapshot, but menus still need input.
+            self.input.snapshot();
+        }
+        self.accumulated_frame_time += scaled_frame_time;
+        let mut steps = 0;
+        while self.accumulated_frame_time >= self.delta_time {
+            if steps >= self.max_substeps {
+                // Keep the fraction of a step so interpolation stays smooth.
+                let excess = self.accumulated_frame_time - self.accumulated_frame_time % self.delta_time;
+                self.accumulated_frame_time -= excess;
+                dropped += excess;
+                break;
+            }
+            self.fixed_update();
+            self.accumulated_frame_time -= self.delta_time;
+            steps += 1;
+        }
+        if dropped > 0.0 {
+            log::debug!("Frame took {}s, dropped {}s of simulation.", frame_time, dropped);
+            self.dropped_time += dropped;
+            self.call_hooks(|hooks, game| hooks.on_time_dropped(game, dropped));
+        }
+        let alpha = self.alpha();
+        self.call_hooks(|hooks, game| hooks.on_update(game, scaled_frame_time, alpha));
+        steps
+    }
+
+    // Reads the clock and advances by however much time passed since the previous tick.
+    pub fn tick(&mut self) -> u32 {
+        let now = self.clock.now();
+        let frame_time = match self.last_tick {
+            Some(last_tick) => now.saturating_sub(last_tick).as_secs_f32(),
+            None => 0.0,
+        };
+        self.last_tick = Some(now);
+        self.advance(frame_time)
+    }
+
+    // Runs exactly `steps` fixed updates, bypassing the accumulator.
+    pub fn step(&mut self, steps: u32) {
+        self.start();
+        for _ in 0..steps {
+            self.fixed_update();
+        }
+    }
+
+    // Simulates `frames` frames at target_fps without creating a window or surface.
+    pub fn run_headless(&mut self, frames: u32) {
+        let frame_time = 1.0 / (self.game_info.target_fps as f32);
+        self.start();
+        for _ in 0..frames {
+            self.advance(frame_time);
+        }
+    }
+
+    pub fn run(self) {
+        if let Err(e) = self.try_run() {
+            log::error!("{}", e);
+            eprintln!("{}", e);
+        }
+    }
+
+    // Only returns if startup fails, once the event loop is running it takes over the thread.
+    pub fn try_run(mut self) -> Result<(), RueError> {
         println!("Rue Game Initialized!\nGame Info: {:?}", self.game_info);
 
-        env_logger::init();
+        let _ = env_logger::try_init();
         let event_loop = EventLoop::new();
         let window = Rc::new(WindowBuilder::new()
-            .build(&event_loop)
-            .unwrap());
+            .build(&event_loop)?);
         window.set_title(&self.game_info.name);
         let phys_size = PhysicalSize::new(self.game_info.window_config.window_size.0, self.game_info.window_config.window_size.1);
         window.set_inner_size(phys_size);
         window.set_resizable(self.game_info.window_config.resizable);
+        self.input.set_scale_factor(window.scale_factor());
         self.event_loop = Some(event_loop);
 
-
-        let mut renderer = Rc::new(RefCell::new(pollster::block_on(Renderer::new(window, self.render_config))));
+        let render_config = self.render_config.get_or_insert_with(|| Rc::new(RefCell::new(RenderConfig::default()))).clone();
+        // A present mode set in the config wins. Capped pacing is left to the limiter, so it stays on Fifo.
+        if render_config.borrow().present_mode.is_none() {
+            render_config.borrow_mut().present_mode = match self.game_info.frame_pacing() {
+                FramePacing::Uncapped => Some(wgpu::PresentMode::Immediate),
+                FramePacing::VSync | FramePacing::Capped(_) => Some(wgpu::PresentMode::Fifo),
+            };
+        }
+        let renderer = Rc::new(RefCell::new(pollster::block_on(Renderer::new(window, Some(render_config)))?));
         self.renderer = Some(renderer.clone());
 
-        let min_delta: f32 = 1.0 / (self.game_info.target_fps as f32);
-        println!("{}. {}", self.game_info.target_fps, min_delta);
-        let mut current_time = std::time::Instant::now();
-        let mut accumulated_frame_time:f32 = 0.0;
-        let mut dir:f64 = 1.0;
-        match self.event_loop {
+        log::info!("Simulating at {} updates per second with {:?} frame pacing.", 1.0 / self.delta_time, self.game_info.frame_pacing());
+        let mut frame_limiter = match self.game_info.frame_pacing() {
+            FramePacing::Capped(fps) => Some(FrameLimiter::new(fps)),
+            _ => None,
+        };
+        let mut screenshot_requested = false;
+        self.start();
+        self.tick();
+        match self.event_loop.take() {
             Some(event_loop) => {
                 event_loop.run(move |event, _, control_flow| {
                     *control_flow = ControlFlow::Poll;
@@ -173,7 +616,8 @@ impl<T: ActorTypes + Clone + 'static > Game<T> {
                             event,
                             window_id,
                         } => {
-                            if window_id == renderer.borrow().window.id() {
+                            if renderer.borrow().window.as_ref().map(|window| window.id()) == Some(window_id) {
+                                self.input.handle_window_event(&event);
                                 match event {
                                     WindowEvent::CloseRequested => {
                                         *control_flow = ControlFlow::Exit;
@@ -184,48 +628,53 @@ impl<T: ActorTypes + Clone + 'static > Game<T> {
                                     WindowEvent::ScaleFactorChanged { new_inner_size: phys_size, .. } => {
                                         renderer.borrow_mut().resize(*phys_size);
                                     },
+                                    WindowEvent::KeyboardInput {
+                                        input: KeyboardInput { state: ElementState::Pressed, virtual_keycode: Some(key), .. },
+                                        ..
+                                    } if Some(key) == self.screenshot_key => {
+                                        screenshot_requested = true;
+                                    },
                                     _ => {}
                                 }
                             }
                         }
                         Event::MainEventsCleared => {
-        
-                            let new_time = std::time::Instant::now();
-                            let frame_time = (new_time - current_time).as_secs_f32();
-                            current_time = new_time;
-                            
-                            accumulated_frame_time += frame_time;
-        
-                            while accumulated_frame_time > self.delta_time {
-                                self.room_manager.edit_actor(|s_manager| {
-                                    if let CoreSystems::SceneManager(s) = s_manager {
-                                        s.cur_scene.push_event(creek::GlobalEventType::Update(self.delta_time), None);
-                                        s.cur_scene.propagate_events();
-                                    }
-                                });
-                                accumulated_frame_time -= self.delta_time;
-                                self.total_delta_time += self.delta_time;
-                                //overall_time += delta;
-                                if renderer.borrow().render_config.borrow().clear_color.r > 1.0 {
-                                    dir = -1.0;
-                                }
-                                else if renderer.borrow().render_config.borrow().clear_color.r < 0.0 {
-                                    dir = 1.0;
+                            self.tick();
+
+                            let render_result = {
+                                let mut renderer = renderer.borrow_mut();
+                                let mut ctx = RenderContext::new(&mut renderer, self.alpha());
+                                if let Some(hooks) = self.hooks.as_mut() {
+                                    hooks.on_render(&mut ctx);
                                 }
-                                renderer.borrow_mut().render_config.borrow_mut().clear_color.r += (self.delta_time as f64) * dir;
-                            }
-                            
-                            match renderer.borrow_mut().render(vec![
-                                RenderableInstance { position: Vector2::new(0.0, 0.0), rotation: Quaternion::new(0.0, 0.0, 0.0, 0.0) }
-                            ]) {
+                                ctx.finish()
+                            };
+                            match render_result {
+                                Ok(_) if screenshot_requested => {
+                                    screenshot_requested = false;
+                                    match renderer.borrow().capture_frame(&self.screenshot_dir) {
+                                        Ok(path) => log::info!("Saved screenshot to {}", path.display()),
+                                        Err(e) => log::error!("{}", e),
+                                    }
+                                },
                                 Ok(_) => {}
                                 // Reconfigure the surface if lost
-                                Err(wgpu::SurfaceError::Lost) => renderer.borrow_mut().resize(renderer.borrow().window.inner_size()),
+                                Err(wgpu::SurfaceError::Lost) => {
+                                    let size = renderer.borrow().size();
+                                    renderer.borrow_mut().resize(size);
+                                },
                                 // The system is out of memory, we should probably quit
                                 Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
                                 // All other errors (Outdated, Timeout) should be resolved by the next frame
                                 Err(e) => eprintln!("{:?}", e),
                             }
+
+                            if let Some(limiter) = frame_limiter.as_mut() {
+                                limiter.wait();
+                            }
+                        }
+                        Event::LoopDestroyed => {
+                            self.stop();
                         }
                         _ => (),
                     }
@@ -235,5 +684,258 @@ impl<T: ActorTypes + Clone + 'static > Game<T> {
                     log::error!("An error occured when trying to run the game. Event loop not initialized correctly.");
             }
         }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::time::ManualClock;
+
+    #[derive(Clone)]
+    enum TestActors {}
+
+    impl ActorTypes for TestActors {
+        fn propogate_global_event(&mut self, _event:&GlobalEvent) -> Option<&Vec<CreekAction>> {
+            None
+        }
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    #[test]
+    fn run_headless_runs_one_fixed_update_per_frame() {
+        let mut game = Game::<TestActors>::new(GameInfo::default());
+        for frame in 1..=30 {
+            game.run_headless(1);
+            assert_eq!(game.time().fixed_ticks(), frame);
+            assert_eq!(game.time().frames(), frame);
+            assert!(close(game.total_delta_time, frame as f32 / 60.0));
+        }
+        assert!(game.running);
+        assert_eq!(game.scene_count(), 1);
+        assert!(game.cur_scene().is_some());
+    }
+
+    struct RecordNotifications(Rc<RefCell<Vec<SceneNotification>>>);
+
+    impl GameHooks<TestActors> for RecordNotifications {
+        fn on_scene_notification(&mut self, _scene: &mut Creek<TestActors>, notification: SceneNotification) {
+            self.0.borrow_mut().push(notification);
+        }
+    }
+
+    #[test]
+    fn scene_stack_keeps_its_base_scene_and_notifies_hooks() {
+        use SceneNotification::*;
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut game = Game::<TestActors>::new(GameInfo::default()).with_hooks(RecordNotifications(log.clone()));
+
+        game.send_scene_event(SceneEvent::Pop);
+        assert_eq!(game.scene_count(), 1);
+        assert!(log.borrow().is_empty());
+
+        game.send_scene_event(SceneEvent::Push(Creek::new()));
+        game.send_scene_event(SceneEvent::Push(Creek::new()));
+        assert_eq!(game.scene_count(), 3);
+        assert_eq!(*log.borrow(), [Pause, Enter, Pause, Enter]);
+
+        log.borrow_mut().clear();
+        game.send_scene_event(SceneEvent::Replace(Creek::new()));
+        assert_eq!(game.scene_count(), 3);
+        assert_eq!(*log.borrow(), [Exit, Enter]);
+
+        log.borrow_mut().clear();
+        game.send_scene_event(SceneEvent::Clear);
+        assert_eq!(game.scene_count(), 1);
+        assert_eq!(*log.borrow(), [Exit, Exit, Resume]);
+
+        log.borrow_mut().clear();
+        game.send_scene_event(SceneEvent::Clear);
+        assert_eq!(game.scene_count(), 1);
+        assert!(log.borrow().is_empty());
+        assert!(game.cur_scene().is_some());
+    }
+
+    struct RecordDropped(Rc<RefCell<Vec<f32>>>);
+
+    impl GameHooks<TestActors> for RecordDropped {
+        fn on_time_dropped(&mut self, _game: &mut Game<TestActors>, dropped: f32) {
+            self.0.borrow_mut().push(dropped);
+        }
+    }
+
+    // Power of two rates and frame times keep the accumulator exact.
+    fn clocked_game(clock: &ManualClock, dropped: &Rc<RefCell<Vec<f32>>>) -> Game<TestActors> {
+        let mut game = Game::<TestActors>::new(GameInfo::default())
+            .with_simulation_rate(64)
+            .with_max_frame_time(0.25)
+            .with_max_substeps(4)
+            .with_clock(clock.clone())
+            .with_hooks(RecordDropped(dropped.clone()));
+        game.start();
+        assert_eq!(game.tick(), 0);
+        game
+    }
+
+    #[test]
+    fn substeps_are_capped_at_max_substeps() {
+        let clock = ManualClock::new();
+        let dropped = Rc::new(RefCell::new(Vec::new()));
+        let mut game = clocked_game(&clock, &dropped);
+
+        clock.advance(Duration::from_secs_f32(0.125));
+        assert_eq!(game.tick(), 4);
+        assert_eq!(game.time().fixed_ticks(), 4);
+        assert_eq!(*dropped.borrow(), [0.0625]);
+        assert_eq!(game.dropped_time, 0.0625);
+
+        clock.advance(Duration::from_secs_f32(0.03125));
+        assert_eq!(game.tick(), 2);
+        assert_eq!(dropped.borrow().len(), 1);
+    }
+
+    #[test]
+    fn long_frames_are_clamped_to_max_frame_time() {
+        let clock = ManualClock::new();
+        let dropped = Rc::new(RefCell::new(Vec::new()));
+        let mut game = clocked_game(&clock, &dropped).with_max_substeps(64);
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(game.tick(), 16);
+        assert_eq!(game.total_delta_time, 0.25);
+        assert_eq!(game.time().delta(), 0.25);
+        assert_eq!(game.time().unscaled_delta(), 1.0);
+        assert_eq!(game.time().unscaled_elapsed(), 1.0);
+        assert_eq!(*dropped.borrow(), [0.75]);
+        assert_eq!(game.dropped_time, 0.75);
+    }
+
+    #[test]
+    fn dropped_time_is_in_scaled_seconds() {
+        let clock = ManualClock::new();
+        let dropped = Rc::new(RefCell::new(Vec::new()));
+        let mut game = clocked_game(&clock, &dropped);
+        game.set_time_scale(0.5);
+
+        // 0.75s over max_frame_time plus 4 of the 8 steps the clamped frame needs, all at half speed.
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(game.tick(), 4);
+        assert_eq!(*dropped.borrow(), [0.375 + 0.0625]);
+        assert_eq!(game.dropped_time, 0.4375);
+
+        game.pause();
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(game.tick(), 0);
+        assert_eq!(dropped.borrow().len(), 1);
+    }
+
+    struct RecordUpdates(Rc<RefCell<Vec<f32>>>);
+
+    impl GameHooks<TestActors> for RecordUpdates {
+        fn on_update(&mut self, _game: &mut Game<TestActors>, delta_time: f32, _alpha: f32) {
+            self.0.borrow_mut().push(delta_time);
+        }
+    }
+
+    #[test]
+    fn on_update_sees_the_clamped_scaled_frame_time() {
+        let updates = Rc::new(RefCell::new(Vec::new()));
+        let mut game = Game::<TestActors>::new(GameInfo::default())
+            .with_max_frame_time(0.25)
+            .with_hooks(RecordUpdates(updates.clone()));
+        game.advance(0.125);
+        game.advance(5.0);
+        game.set_time_scale(0.5);
+        game.advance(5.0);
+        game.pause();
+        game.advance(0.125);
+        assert_eq!(*updates.borrow(), [0.125, 0.25, 0.125, 0.0]);
+        assert_eq!(game.time().unscaled_delta(), 0.125);
+    }
+
+    #[test]
+    fn frame_pacing_defaults_to_target_fps() {
+        let game_info = GameInfo { target_fps: 144, ..GameInfo::default() };
+        assert_eq!(game_info.frame_pacing(), FramePacing::Capped(144));
+        let game = Game::<TestActors>::new(game_info).with_frame_pacing(FramePacing::VSync);
+        assert_eq!(game.game_info.frame_pacing(), FramePacing::VSync);
+    }
+
+    // Remembers where each notified scene lives, to tell which scene a notification reached.
+    type SceneLog = Rc<RefCell<Vec<(SceneNotification, *const Creek<TestActors>)>>>;
+
+    struct RecordScenes(SceneLog);
+
+    impl GameHooks<TestActors> for RecordScenes {
+        fn on_scene_notification(&mut self, scene: &mut Creek<TestActors>, notification: SceneNotification) {
+            self.0.borrow_mut().push((notification, scene as *const _));
+        }
+    }
+
+    #[test]
+    fn scene_events_through_the_creek_path_reach_their_own_scenes() {
+        use SceneNotification::*;
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut game = Game::<TestActors>::new(GameInfo::default()).with_hooks(RecordScenes(log.clone()));
+        if let Some(CoreSystems::SceneManager(s)) = &mut *game.scene() {
+            s.receive_event(SceneEvent::Push(Creek::new()));
+            s.receive_event(SceneEvent::Pop);
+            s.receive_event(SceneEvent::Push(Creek::new()));
+            s.receive_event(SceneEvent::Replace(Creek::new()));
+        }
+        assert!(log.borrow().is_empty());
+
+        // The queue is drained by the next fixed update.
+        game.step(1);
+        assert_eq!(game.scene_count(), 2);
+        let log = log.borrow();
+        let notifications: Vec<SceneNotification> = log.iter().map(|(notification, _)| *notification).collect();
+        assert_eq!(notifications, [Pause, Enter, Exit, Resume, Pause, Enter, Exit, Enter]);
+        // Only the last Enter belongs to the scene that is still on top, the others went to scenes already gone.
+        let top = &*game.cur_scene().unwrap() as *const _;
+        let entered_top: Vec<bool> = log.iter().filter(|(notification, _)| *notification == Enter).map(|(_, scene)| *scene == top).collect();
+        assert_eq!(entered_top, [false, false, true]);
+        // Every scene that entered also exited, apart from the one on top.
+        assert_eq!(log[1].1, log[2].1);
+        assert_eq!(log[5].1, log[6].1);
+    }
+
+    struct PushOnFixedUpdate(Rc<RefCell<Vec<SceneNotification>>>);
+
+    impl GameHooks<TestActors> for PushOnFixedUpdate {
+        fn on_fixed_update(&mut self, game: &mut Game<TestActors>, _delta_time: f32) {
+            if game.scene_count() == 1 {
+                game.send_scene_event(SceneEvent::Push(Creek::new()));
+            }
+        }
+
+        fn on_scene_notification(&mut self, _scene: &mut Creek<TestActors>, notification: SceneNotification) {
+            self.0.borrow_mut().push(notification);
+        }
+    }
+
+    #[test]
+    fn scene_events_sent_from_hooks_are_delivered() {
+        use SceneNotification::*;
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut game = Game::<TestActors>::new(GameInfo::default()).with_hooks(PushOnFixedUpdate(log.clone()));
+        game.step(1);
+        assert_eq!(game.scene_count(), 2);
+        assert_eq!(*log.borrow(), [Pause, Enter]);
+    }
+
+    #[test]
+    fn step_bypasses_the_accumulator() {
+        let mut game = Game::<TestActors>::new(GameInfo::default()).with_simulation_rate(50);
+        game.step(7);
+        assert_eq!(game.time().fixed_ticks(), 7);
+        assert_eq!(game.time().frames(), 0);
+        assert!(close(game.total_delta_time, 7.0 * 0.02));
+        assert_eq!(game.scene_count(), 1);
     }
 }
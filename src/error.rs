@@ -0,0 +1,57 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum RueError {
+    WindowCreation(winit::error::OsError),
+    AdapterNotFound,
+    DeviceRequest(wgpu::RequestDeviceError),
+    IncompatibleSurface,
+    // A value passed to a setter is out of range. wgpu 0.12 panics on its own when Surface::configure fails.
+    InvalidSetting(String),
+    ShaderCompilation(String),
+    Material(String),
+    Readback(String),
+    Asset { path: String, message: String },
+    Screenshot { path: String, message: String },
+}
+
+pub type RueResult<T> = Result<T, RueError>;
+
+impl fmt::Display for RueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RueError::WindowCreation(e) => write!(f, "Could not create a window: {}", e),
+            RueError::AdapterNotFound => write!(f, "No graphics adapter supporting Vulkan, Metal, DirectX 12 or OpenGL was found."),
+            RueError::DeviceRequest(e) => write!(f, "The graphics adapter refused to create a device: {}", e),
+            RueError::IncompatibleSurface => write!(f, "The graphics adapter cannot present to this window."),
+            RueError::InvalidSetting(e) => write!(f, "Invalid setting: {}", e),
+            RueError::ShaderCompilation(e) => write!(f, "Shader failed to compile: {}", e),
+            RueError::Material(e) => write!(f, "Invalid material: {}", e),
+            RueError::Readback(e) => write!(f, "Could not read the frame back from the GPU: {}", e),
+            RueError::Asset { path, message } => write!(f, "Could not load asset '{}': {}", path, message),
+            RueError::Screenshot { path, message } => write!(f, "Could not save screenshot '{}': {}", path, message),
+        }
+    }
+}
+
+impl std::error::Error for RueError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RueError::WindowCreation(e) => Some(e),
+            RueError::DeviceRequest(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<winit::error::OsError> for RueError {
+    fn from(e: winit::error::OsError) -> Self {
+        RueError::WindowCreation(e)
+    }
+}
+
+impl From<wgpu::RequestDeviceError> for RueError {
+    fn from(e: wgpu::RequestDeviceError) -> Self {
+        RueError::DeviceRequest(e)
+    }
+}
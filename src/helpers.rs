@@ -1,5 +1,5 @@
 pub mod colors {
-    #[derive(Copy, Clone)]
+    #[derive(Copy, Clone, Debug, PartialEq)]
     pub struct Color {
         pub r: f64,
         pub g: f64,
@@ -42,14 +42,10 @@ pub mod colors {
 }
 
 pub mod math {
-    use cgmath::Quaternion;
+    use cgmath::{Quaternion, Rad, Rotation3};
 
+    // Counter-clockwise rotation around the z axis, matching Transform2D::rotation.
     pub fn euler_roll_to_quat(roll:f32) -> Quaternion<f32> {
-        let cos_roll = (roll * 0.5).cos();
-        let sin_roll = (roll * 0.5).sin();
-
-        let sum_cos_sin = cos_roll + sin_roll;
-        let diff_cos_sin = sin_roll - cos_roll;
-        Quaternion::new(sum_cos_sin, diff_cos_sin, sum_cos_sin, diff_cos_sin)
+        Quaternion::from_angle_z(Rad(roll))
     }
 }
@@ -0,0 +1,395 @@
+use std::{
+    cell::RefCell, collections::{HashMap, HashSet}, rc::Rc,
+};
+
+use cgmath::Vector2;
+use winit::{
+    dpi::{LogicalPosition, PhysicalPosition},
+    event::{ElementState, KeyboardInput, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent},
+};
+
+// Pixel scroll deltas (touchpads) are converted to lines so both kinds of device report the same unit.
+const PIXELS_PER_SCROLL_LINE: f32 = 20.0;
+
+// winit has no gamepad support, so gamepad state is fed in by the game through Input::set_button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftShoulder,
+    RightShoulder,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Button {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AxisBinding {
+    pub negative: Button,
+    pub positive: Button,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ActionMap {
+    actions: HashMap<String, Vec<Button>>,
+    axes: HashMap<String, Vec<AxisBinding>>,
+}
+
+impl ActionMap {
+    pub fn new() -> Self {
+        ActionMap::default()
+    }
+
+    pub fn with_action(mut self, name: &str, buttons: &[Button]) -> Self {
+        for button in buttons {
+            self.bind_action(name, *button);
+        }
+        self
+    }
+
+    pub fn with_axis(mut self, name: &str, negative: Button, positive: Button) -> Self {
+        self.bind_axis(name, negative, positive);
+        self
+    }
+
+    pub fn bind_action(&mut self, name: &str, button: Button) {
+        self.actions.entry(name.to_string()).or_default().push(button);
+    }
+
+    pub fn bind_axis(&mut self, name: &str, negative: Button, positive: Button) {
+        self.axes.entry(name.to_string()).or_default().push(AxisBinding { negative, positive });
+    }
+
+    pub fn unbind(&mut self, name: &str) {
+        self.actions.remove(name);
+        self.axes.remove(name);
+    }
+
+    pub fn action_buttons(&self, name: &str) -> &[Button] {
+        self.actions.get(name).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn axis_bindings(&self, name: &str) -> &[AxisBinding] {
+        self.axes.get(name).map(Vec::as_slice).unwrap_or(&[])
+    }
+}
+
+// Snapshot of the input devices taken at the start of a fixed update.
+#[derive(Debug, Clone)]
+pub struct InputState {
+    held: HashSet<Button>,
+    pressed: HashSet<Button>,
+    released: HashSet<Button>,
+    pub cursor_position: PhysicalPosition<f64>,
+    pub cursor_logical_position: LogicalPosition<f64>,
+    pub scroll_delta: Vector2<f32>,
+    action_map: Rc<ActionMap>,
+}
+
+pub type InputHandle = Rc<RefCell<InputState>>;
+
+impl Default for InputState {
+    fn default() -> Self {
+        InputState {
+            held: HashSet::new(),
+            pressed: HashSet::new(),
+            released: HashSet::new(),
+            cursor_position: PhysicalPosition::new(0.0, 0.0),
+            cursor_logical_position: LogicalPosition::new(0.0, 0.0),
+            scroll_delta: Vector2::new(0.0, 0.0),
+            action_map: Rc::new(ActionMap::default()),
+        }
+    }
+}
+
+impl InputState {
+    pub fn is_held(&self, button: Button) -> bool {
+        self.held.contains(&button)
+    }
+
+    pub fn is_pressed(&self, button: Button) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    pub fn is_released(&self, button: Button) -> bool {
+        self.released.contains(&button)
+    }
+
+    pub fn is_key_held(&self, key: VirtualKeyCode) -> bool {
+        self.is_held(Button::Key(key))
+    }
+
+    pub fn is_key_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.is_pressed(Button::Key(key))
+    }
+
+    pub fn is_key_released(&self, key: VirtualKeyCode) -> bool {
+        self.is_released(Button::Key(key))
+    }
+
+    pub fn action_held(&self, name: &str) -> bool {
+        self.action_map.action_buttons(name).iter().any(|b| self.is_held(*b))
+    }
+
+    pub fn action_pressed(&self, name: &str) -> bool {
+        self.action_map.action_buttons(name).iter().any(|b| self.is_pressed(*b))
+    }
+
+    pub fn action_released(&self, name: &str) -> bool {
+        self.action_map.action_buttons(name).iter().any(|b| self.is_released(*b))
+    }
+
+    // Value in [-1, 1] summed over every binding of the axis.
+    pub fn axis(&self, name: &str) -> f32 {
+        let value: f32 = self.action_map.axis_bindings(name).iter().map(|binding| {
+            let mut value = 0.0;
+            if self.is_held(binding.negative) {
+                value -= 1.0;
+            }
+            if self.is_held(binding.positive) {
+                value += 1.0;
+            }
+            value
+        }).sum();
+        value.clamp(-1.0, 1.0)
+    }
+
+    pub fn action_map(&self) -> &ActionMap {
+        &self.action_map
+    }
+}
+
+// Collects window events between fixed updates and publishes them as an InputState.
+pub struct Input {
+    down: HashSet<Button>,
+    pressed: HashSet<Button>,
+    released: HashSet<Button>,
+    cursor_position: PhysicalPosition<f64>,
+    scroll_delta: Vector2<f32>,
+    scale_factor: f64,
+    action_map: Rc<ActionMap>,
+    state: InputHandle,
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        Input {
+            down: HashSet::new(),
+            pressed: HashSet::new(),
+            released: HashSet::new(),
+            cursor_position: PhysicalPosition::new(0.0, 0.0),
+            scroll_delta: Vector2::new(0.0, 0.0),
+            scale_factor: 1.0,
+            action_map: Rc::new(ActionMap::default()),
+            state: Rc::new(RefCell::new(InputState::default())),
+        }
+    }
+}
+
+impl Input {
+    pub fn new() -> Self {
+        Input::default()
+    }
+
+    pub fn handle(&self) -> InputHandle {
+        self.state.clone()
+    }
+
+    pub fn set_action_map(&mut self, action_map: ActionMap) {
+        self.action_map = Rc::new(action_map);
+        self.state.borrow_mut().action_map = self.action_map.clone();
+    }
+
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
+    pub fn set_button(&mut self, button: Button, is_down: bool) {
+        if is_down {
+            if self.down.insert(button) {
+                self.pressed.insert(button);
+            }
+        }
+        else if self.down.remove(&button) {
+            self.released.insert(button);
+        }
+    }
+
+    pub fn handle_window_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput { input: KeyboardInput { virtual_keycode: Some(key), state, .. }, .. } => {
+                self.set_button(Button::Key(*key), *state == ElementState::Pressed);
+            },
+            WindowEvent::MouseInput { state, button, .. } => {
+                self.set_button(Button::Mouse(*button), *state == ElementState::Pressed);
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = *position;
+            },
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.scroll_delta += match delta {
+                    MouseScrollDelta::LineDelta(x, y) => Vector2::new(*x, *y),
+                    MouseScrollDelta::PixelDelta(p) => Vector2::new(p.x as f32, p.y as f32) / PIXELS_PER_SCROLL_LINE,
+                };
+            },
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                self.scale_factor = *scale_factor;
+            },
+            WindowEvent::Focused(false) => {
+                // Key releases are not delivered to unfocused windows, so let go of everything.
+                for button in self.down.drain() {
+                    self.released.insert(button);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    // Publishes everything collected since the previous snapshot to the shared InputState.
+    pub fn snapshot(&mut self) {
+        let mut state = self.state.borrow_mut();
+        state.held.clone_from(&self.down);
+        state.pressed = std::mem::take(&mut self.pressed);
+        state.released = std::mem::take(&mut self.released);
+        state.cursor_position = self.cursor_position;
+        state.cursor_logical_position = self.cursor_position.to_logical(self.scale_factor);
+        state.scroll_delta = std::mem::replace(&mut self.scroll_delta, Vector2::new(0.0, 0.0));
+        state.action_map = self.action_map.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JUMP: Button = Button::Key(VirtualKeyCode::Space);
+    const LEFT: Button = Button::Key(VirtualKeyCode::A);
+    const RIGHT: Button = Button::Key(VirtualKeyCode::D);
+    const PAD_RIGHT: Button = Button::Gamepad(GamepadButton::DPadRight);
+
+    #[test]
+    fn buttons_go_from_pressed_to_held_to_released() {
+        let mut input = Input::new();
+        let state = input.handle();
+
+        input.set_button(JUMP, true);
+        input.snapshot();
+        assert!(state.borrow().is_pressed(JUMP) && state.borrow().is_held(JUMP) && !state.borrow().is_released(JUMP));
+
+        input.snapshot();
+        assert!(!state.borrow().is_pressed(JUMP) && state.borrow().is_held(JUMP));
+
+        input.set_button(JUMP, false);
+        input.snapshot();
+        assert!(state.borrow().is_released(JUMP) && !state.borrow().is_held(JUMP));
+
+        input.snapshot();
+        assert!(!state.borrow().is_released(JUMP));
+    }
+
+    #[test]
+    fn a_tap_between_snapshots_is_not_lost() {
+        let mut input = Input::new();
+        let state = input.handle();
+        input.set_button(JUMP, true);
+        input.set_button(JUMP, false);
+        input.snapshot();
+        assert!(state.borrow().is_pressed(JUMP) && state.borrow().is_released(JUMP) && !state.borrow().is_held(JUMP));
+    }
+
+    #[allow(deprecated)]
+    fn key_event(key: VirtualKeyCode, state: ElementState) -> WindowEvent<'static> {
+        WindowEvent::KeyboardInput {
+            device_id: unsafe { winit::event::DeviceId::dummy() },
+            input: KeyboardInput {
+                scancode: 0,
+                state,
+                virtual_keycode: Some(key),
+                modifiers: winit::event::ModifiersState::empty(),
+            },
+            is_synthetic: false,
+        }
+    }
+
+    #[test]
+    fn key_repeat_does_not_press_again() {
+        let mut input = Input::new();
+        let state = input.handle();
+        input.handle_window_event(&key_event(VirtualKeyCode::Space, ElementState::Pressed));
+        input.snapshot();
+        assert!(state.borrow().is_pressed(JUMP));
+        // Held keys keep sending Pressed events at the OS repeat rate.
+        input.handle_window_event(&key_event(VirtualKeyCode::Space, ElementState::Pressed));
+        input.handle_window_event(&key_event(VirtualKeyCode::Space, ElementState::Pressed));
+        input.snapshot();
+        assert!(!state.borrow().is_pressed(JUMP) && state.borrow().is_held(JUMP));
+    }
+
+    #[test]
+    fn losing_focus_releases_everything() {
+        let mut input = Input::new();
+        let state = input.handle();
+        input.set_button(LEFT, true);
+        input.set_button(Button::Mouse(MouseButton::Left), true);
+        input.snapshot();
+
+        input.handle_window_event(&WindowEvent::Focused(false));
+        input.snapshot();
+        let state = state.borrow();
+        assert!(!state.is_held(LEFT) && state.is_released(LEFT));
+        assert!(!state.is_held(Button::Mouse(MouseButton::Left)) && state.is_released(Button::Mouse(MouseButton::Left)));
+    }
+
+    #[test]
+    fn axes_sum_their_bindings_and_clamp() {
+        let mut input = Input::new();
+        input.set_action_map(ActionMap::new()
+            .with_axis("move", LEFT, RIGHT)
+            .with_axis("move", Button::Gamepad(GamepadButton::DPadLeft), PAD_RIGHT)
+            .with_action("jump", &[JUMP, Button::Gamepad(GamepadButton::South)]));
+        let state = input.handle();
+
+        input.snapshot();
+        assert_eq!(state.borrow().axis("move"), 0.0);
+
+        input.set_button(RIGHT, true);
+        input.snapshot();
+        assert_eq!(state.borrow().axis("move"), 1.0);
+
+        // Both bindings pointing right still give 1.
+        input.set_button(PAD_RIGHT, true);
+        input.snapshot();
+        assert_eq!(state.borrow().axis("move"), 1.0);
+
+        // Opposite directions cancel out.
+        input.set_button(PAD_RIGHT, false);
+        input.set_button(LEFT, true);
+        input.snapshot();
+        assert_eq!(state.borrow().axis("move"), 0.0);
+
+        input.set_button(RIGHT, false);
+        input.snapshot();
+        assert_eq!(state.borrow().axis("move"), -1.0);
+        assert_eq!(state.borrow().axis("unbound"), 0.0);
+
+        input.set_button(Button::Gamepad(GamepadButton::South), true);
+        input.snapshot();
+        assert!(state.borrow().action_pressed("jump") && state.borrow().action_held("jump"));
+    }
+}
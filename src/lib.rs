@@ -2,4 +2,7 @@ pub mod core;
 pub mod actors;
 pub mod renderer;
 pub mod math;
-pub mod helpers;
\ No newline at end of file
+pub mod helpers;
+pub mod error;
+pub mod input;
+pub mod time;
\ No newline at end of file
@@ -1 +1,222 @@
-pub struct Vec2(f32, f32);
\ No newline at end of file
+use cgmath::{Matrix3, Matrix4, SquareMatrix, Vector2, Vector3};
+
+pub struct Vec2(f32, f32);
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub const fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Rect { x, y, width, height }
+    }
+}
+
+// Placement of something in 2D. It is scaled and rotated around `pivot`, which then ends up on `translation`.
+// Composed transforms can shear when scales are non-uniform, so they are kept as affine Matrix3s instead.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform2D {
+    pub translation: Vector2<f32>,
+    // Counter-clockwise, in radians.
+    pub rotation: f32,
+    pub scale: Vector2<f32>,
+    // In local space, before scaling.
+    pub pivot: Vector2<f32>,
+}
+
+impl Transform2D {
+    pub const IDENTITY: Transform2D = Transform2D {
+        translation: Vector2 { x: 0.0, y: 0.0 },
+        rotation: 0.0,
+        scale: Vector2 { x: 1.0, y: 1.0 },
+        pivot: Vector2 { x: 0.0, y: 0.0 },
+    };
+
+    pub fn new(translation: Vector2<f32>) -> Self {
+        Transform2D {
+            translation,
+            ..Transform2D::IDENTITY
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: Vector2<f32>) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_pivot(mut self, pivot: Vector2<f32>) -> Self {
+        self.pivot = pivot;
+        self
+    }
+
+    // Rotation times scale, the part of the matrix that applies to directions.
+    fn linear(&self) -> Matrix3<f32> {
+        let (sin, cos) = self.rotation.sin_cos();
+        Matrix3::new(
+            cos * self.scale.x, sin * self.scale.x, 0.0,
+            -sin * self.scale.y, cos * self.scale.y, 0.0,
+            0.0, 0.0, 1.0,
+        )
+    }
+
+    // Affine matrix acting on (x, y, 1) columns.
+    pub fn matrix(&self) -> Matrix3<f32> {
+        let mut matrix = self.linear();
+        let offset = self.translation - self.transform_vector(self.pivot);
+        matrix.z = Vector3::new(offset.x, offset.y, 1.0);
+        matrix
+    }
+
+    // The same transform as a 3D matrix for the GPU, leaving z untouched.
+    pub fn to_matrix4(&self) -> Matrix4<f32> {
+        affine_to_matrix4(&self.matrix())
+    }
+
+    // None when a scale axis is zero, everything is flattened onto a line then.
+    pub fn inverse_matrix(&self) -> Option<Matrix3<f32>> {
+        self.matrix().invert()
+    }
+
+    // Matrix placing `child` inside this transform, so the child is applied first. Chain these for deeper hierarchies.
+    pub fn compose(&self, child: &Transform2D) -> Matrix3<f32> {
+        self.matrix() * child.matrix()
+    }
+
+    pub fn transform_point(&self, point: Vector2<f32>) -> Vector2<f32> {
+        self.translation + self.transform_vector(point - self.pivot)
+    }
+
+    // Directions are rotated and scaled but not moved.
+    pub fn transform_vector(&self, vector: Vector2<f32>) -> Vector2<f32> {
+        let transformed = self.linear() * vector.extend(0.0);
+        Vector2::new(transformed.x, transformed.y)
+    }
+
+    // Local position of a point given in the space this transform places things into.
+    pub fn inverse_transform_point(&self, point: Vector2<f32>) -> Option<Vector2<f32>> {
+        if self.scale.x == 0.0 || self.scale.y == 0.0 {
+            return None;
+        }
+        let (sin, cos) = self.rotation.sin_cos();
+        let offset = point - self.translation;
+        let unrotated = Vector2::new(cos * offset.x + sin * offset.y, -sin * offset.x + cos * offset.y);
+        Some(self.pivot + Vector2::new(unrotated.x / self.scale.x, unrotated.y / self.scale.y))
+    }
+
+    // Blends towards `other`, alpha 0.0 returns self and 1.0 returns other. The rotation takes the shortest way around.
+    pub fn lerp(&self, other: &Transform2D, alpha: f32) -> Transform2D {
+        let turn = (other.rotation - self.rotation + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI;
+        Transform2D {
+            translation: self.translation + (other.translation - self.translation) * alpha,
+            rotation: self.rotation + turn * alpha,
+            scale: self.scale + (other.scale - self.scale) * alpha,
+            pivot: self.pivot + (other.pivot - self.pivot) * alpha,
+        }
+    }
+}
+
+impl Default for Transform2D {
+    fn default() -> Self {
+        Transform2D::IDENTITY
+    }
+}
+
+// Lifts a 2D affine matrix into 3D, z passes through unchanged.
+pub fn affine_to_matrix4(matrix: &Matrix3<f32>) -> Matrix4<f32> {
+    Matrix4::new(
+        matrix.x.x, matrix.x.y, 0.0, 0.0,
+        matrix.y.x, matrix.y.y, 0.0, 0.0,
+        0.0, 0.0, 1.0, 0.0,
+        matrix.z.x, matrix.z.y, 0.0, 1.0,
+    )
+}
+
+// Applies a composed affine matrix to a point.
+pub fn transform_point(matrix: &Matrix3<f32>, point: Vector2<f32>) -> Vector2<f32> {
+    let transformed = matrix * point.extend(1.0);
+    Vector2::new(transformed.x, transformed.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Rotation, Vector4};
+
+    fn close(a: Vector2<f32>, b: Vector2<f32>) -> bool {
+        (a.x - b.x).abs() < 1e-4 && (a.y - b.y).abs() < 1e-4
+    }
+
+    fn sample() -> Transform2D {
+        Transform2D::new(Vector2::new(3.0, -2.0))
+            .with_rotation(0.7)
+            .with_scale(Vector2::new(2.0, 0.5))
+            .with_pivot(Vector2::new(0.3, 0.1))
+    }
+
+    #[test]
+    fn points_round_trip() {
+        let transform = sample();
+        let point = Vector2::new(1.5, -4.0);
+        let transformed = transform.transform_point(point);
+        assert!(close(transformed, transform_point(&transform.matrix(), point)));
+        assert!(close(transform.inverse_transform_point(transformed).unwrap(), point));
+        assert!(close(transform.transform_point(transform.pivot), transform.translation));
+    }
+
+    #[test]
+    fn matrix_round_trips() {
+        let transform = sample();
+        let inverse = transform.inverse_matrix().unwrap();
+        let point = Vector2::new(1.5, -4.0);
+        assert!(close(transform_point(&inverse, transform_point(&transform.matrix(), point)), point));
+        assert!(close(transform_point(&transform.matrix(), transform_point(&inverse, point)), point));
+        let flat = Transform2D::IDENTITY.with_scale(Vector2::new(0.0, 1.0));
+        assert!(flat.inverse_matrix().is_none());
+        assert!(flat.inverse_transform_point(Vector2::new(1.0, 1.0)).is_none());
+    }
+
+    #[test]
+    fn positive_rotation_is_counter_clockwise() {
+        let quarter_turn = Transform2D::IDENTITY.with_rotation(std::f32::consts::FRAC_PI_2);
+        assert!(close(quarter_turn.transform_point(Vector2::new(1.0, 0.0)), Vector2::new(0.0, 1.0)));
+        assert!(close(quarter_turn.transform_point(Vector2::new(0.0, 1.0)), Vector2::new(-1.0, 0.0)));
+    }
+
+    #[test]
+    fn compose_matches_nesting() {
+        let parent = sample();
+        let child = Transform2D::new(Vector2::new(-1.0, 0.5))
+            .with_rotation(-1.2)
+            .with_scale(Vector2::new(0.5, 3.0))
+            .with_pivot(Vector2::new(0.2, -0.4));
+        let point = Vector2::new(0.8, 2.5);
+        let nested = parent.transform_point(child.transform_point(point));
+        assert!(close(transform_point(&parent.compose(&child), point), nested));
+    }
+
+    #[test]
+    fn to_matrix4_matches_matrix() {
+        let transform = sample();
+        let point = Vector2::new(1.5, -4.0);
+        let transformed = transform.to_matrix4() * Vector4::new(point.x, point.y, 0.0, 1.0);
+        assert!(close(Vector2::new(transformed.x, transformed.y), transform.transform_point(point)));
+    }
+
+    #[test]
+    fn euler_roll_to_quat_matches_rotation() {
+        let rotation = 0.9;
+        let quat = crate::helpers::math::euler_roll_to_quat(rotation);
+        let vector = Vector2::new(1.0, 2.0);
+        let rotated = quat.rotate_vector(vector.extend(0.0));
+        assert!(close(Vector2::new(rotated.x, rotated.y), Transform2D::IDENTITY.with_rotation(rotation).transform_vector(vector)));
+    }
+}
@@ -0,0 +1,4 @@
+[[stage(fragment)]]
+fn fragment_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    return textureSample(t_diffuse, s_diffuse, in.tex_coords) * in.vertex_color;
+}
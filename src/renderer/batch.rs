@@ -0,0 +1,291 @@
+use std::{cmp::Ordering, ops::Range};
+
+use super::{RenderableInstanceRaw, texture::TextureHandle, blend::BlendMode, material::MaterialHandle, mesh::MeshHandle};
+
+// Everything that forces a new draw call when it changes between two sprites.
+// Ordered so that pipeline changes, the most expensive, happen least often.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BatchKey {
+    pub material: MaterialHandle,
+    pub blend: BlendMode,
+    pub mesh: MeshHandle,
+    pub texture: TextureHandle,
+}
+
+impl BatchKey {
+    pub fn new(texture: TextureHandle) -> Self {
+        BatchKey {
+            material: MaterialHandle::default(),
+            blend: BlendMode::default(),
+            mesh: MeshHandle::QUAD,
+            texture,
+        }
+    }
+}
+
+// Coarse draw order, every sprite on a layer is drawn on top of the layers before it.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum RenderLayer {
+    Background,
+    #[default]
+    World,
+    Foreground,
+    UI,
+}
+
+impl RenderLayer {
+    pub const COUNT: usize = 4;
+}
+
+// Where a sprite ends up in the frame, later entries are drawn on top of earlier ones.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct DrawOrder {
+    pub layer: RenderLayer,
+    // Order within the layer, higher values are drawn on top.
+    pub z: f32,
+    // World y of the sprite, only used on y-sorted layers where lower sprites are drawn on top.
+    pub y: f32,
+}
+
+impl DrawOrder {
+    pub fn new(layer: RenderLayer, z: f32) -> Self {
+        DrawOrder {
+            layer,
+            z,
+            y: 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DrawBatch {
+    pub key: BatchKey,
+    pub instances: Range<u32>,
+    // Part of the shape index buffer to draw, None draws the whole mesh of the key.
+    pub indices: Option<Range<u32>>,
+}
+
+#[derive(Clone, Debug)]
+struct Submission {
+    order: DrawOrder,
+    key: BatchKey,
+    raw: RenderableInstanceRaw,
+    indices: Option<Range<u32>>,
+}
+
+// Sorts submitted sprites by layer, z, y on y-sorted layers and then batch key, and packs them into one instance array.
+// Sprites that compare equal keep their submission order.
+#[derive(Default)]
+pub struct SpriteBatcher {
+    submissions: Vec<Submission>,
+    instances: Vec<RenderableInstanceRaw>,
+    batches: Vec<DrawBatch>,
+    y_sorted: [bool; RenderLayer::COUNT],
+}
+
+impl SpriteBatcher {
+    pub fn new() -> Self {
+        SpriteBatcher::default()
+    }
+
+    pub fn push(&mut self, key: BatchKey, order: DrawOrder, raw: RenderableInstanceRaw) {
+        self.submissions.push(Submission { order, key, raw, indices: None });
+    }
+
+    // Immediate mode shapes are already in world space, so they are drawn with a single identity instance.
+    // Every range gets its own draw call so shapes can sit between sprites.
+    pub fn push_shapes(&mut self, key: BatchKey, order: DrawOrder, indices: Range<u32>) {
+        self.submissions.push(Submission { order, key, raw: RenderableInstanceRaw::IDENTITY, indices: Some(indices) });
+    }
+
+    // Draws sprites with the same z on `layer` from the top of the world down, for top-down games.
+    pub fn set_y_sort(&mut self, layer: RenderLayer, y_sort: bool) {
+        self.y_sorted[layer as usize] = y_sort;
+    }
+
+    pub fn is_y_sorted(&self, layer: RenderLayer) -> bool {
+        self.y_sorted[layer as usize]
+    }
+
+    fn compare(&self, a: &Submission, b: &Submission) -> Ordering {
+        let y_order = if self.is_y_sorted(a.order.layer) {
+            b.order.y.total_cmp(&a.order.y)
+        }
+        else {
+            Ordering::Equal
+        };
+        a.order.layer.cmp(&b.order.layer)
+            .then(a.order.z.total_cmp(&b.order.z))
+            .then(y_order)
+            .then(a.key.cmp(&b.key))
+    }
+
+    pub fn len(&self) -> usize {
+        self.submissions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.submissions.is_empty()
+    }
+
+    // Sorts the submissions and rebuilds the instance array and batch list.
+    pub fn prepare(&mut self) {
+        let mut submissions = std::mem::take(&mut self.submissions);
+        submissions.sort_by(|a, b| self.compare(a, b));
+        self.submissions = submissions;
+        self.instances.clear();
+        self.batches.clear();
+        for submission in &self.submissions {
+            let index = self.instances.len() as u32;
+            self.instances.push(submission.raw);
+            match self.batches.last_mut() {
+                Some(batch) if batch.key == submission.key && batch.indices.is_none() && submission.indices.is_none() => {
+                    batch.instances.end = index + 1;
+                },
+                _ => self.batches.push(DrawBatch {
+                    key: submission.key,
+                    instances: index..index + 1,
+                    indices: submission.indices.clone(),
+                }),
+            }
+        }
+    }
+
+    pub fn instances(&self) -> &[RenderableInstanceRaw] {
+        &self.instances
+    }
+
+    pub fn batches(&self) -> &[DrawBatch] {
+        &self.batches
+    }
+
+    // Forgets this frame's submissions, keeping the allocations for the next one.
+    // The prepared instances and batches stay around until the next prepare so the frame can be drawn again.
+    pub fn clear(&mut self) {
+        self.submissions.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The tint's red channel tells submissions apart.
+    fn raw(id: u32) -> RenderableInstanceRaw {
+        RenderableInstanceRaw {
+            tint: [id as f32, 1.0, 1.0, 1.0],
+            ..RenderableInstanceRaw::IDENTITY
+        }
+    }
+
+    fn ids(batcher: &SpriteBatcher) -> Vec<u32> {
+        batcher.instances().iter().map(|instance| instance.tint[0] as u32).collect()
+    }
+
+    fn at_y(layer: RenderLayer, y: f32) -> DrawOrder {
+        DrawOrder { y, ..DrawOrder::new(layer, 0.0) }
+    }
+
+    const A: BatchKey = BatchKey {
+        material: MaterialHandle(0),
+        blend: BlendMode::Alpha,
+        mesh: MeshHandle::QUAD,
+        texture: TextureHandle(0),
+    };
+    const B: BatchKey = BatchKey { texture: TextureHandle(1), ..A };
+
+    #[test]
+    fn sorts_by_layer_then_z() {
+        let mut batcher = SpriteBatcher::new();
+        batcher.push(A, DrawOrder::new(RenderLayer::UI, -5.0), raw(0));
+        batcher.push(A, DrawOrder::new(RenderLayer::World, 2.0), raw(1));
+        batcher.push(A, DrawOrder::new(RenderLayer::Background, 10.0), raw(2));
+        batcher.push(A, DrawOrder::new(RenderLayer::World, -1.0), raw(3));
+        batcher.push(A, DrawOrder::new(RenderLayer::Foreground, 0.0), raw(4));
+        batcher.prepare();
+        assert_eq!(ids(&batcher), [2, 3, 1, 4, 0]);
+    }
+
+    #[test]
+    fn y_sorted_layers_draw_lower_sprites_on_top() {
+        let mut batcher = SpriteBatcher::new();
+        batcher.set_y_sort(RenderLayer::World, true);
+        batcher.push(A, at_y(RenderLayer::World, -3.0), raw(0));
+        batcher.push(A, at_y(RenderLayer::World, 5.0), raw(1));
+        batcher.push(A, at_y(RenderLayer::World, 1.0), raw(2));
+        // Other layers ignore y.
+        batcher.push(A, at_y(RenderLayer::UI, -3.0), raw(3));
+        batcher.push(A, at_y(RenderLayer::UI, 5.0), raw(4));
+        // z still beats y.
+        batcher.push(A, DrawOrder { z: 1.0, ..at_y(RenderLayer::World, 10.0) }, raw(5));
+        batcher.prepare();
+        assert_eq!(ids(&batcher), [1, 2, 0, 5, 3, 4]);
+        assert!(batcher.is_y_sorted(RenderLayer::World) && !batcher.is_y_sorted(RenderLayer::UI));
+    }
+
+    #[test]
+    fn equal_submissions_keep_their_order() {
+        let mut batcher = SpriteBatcher::new();
+        for id in 0..8 {
+            batcher.push(A, DrawOrder::default(), raw(id));
+        }
+        batcher.prepare();
+        assert_eq!(ids(&batcher), (0..8).collect::<Vec<_>>());
+        assert_eq!(batcher.batches(), [DrawBatch { key: A, instances: 0..8, indices: None }]);
+    }
+
+    #[test]
+    fn equal_orders_are_grouped_by_key() {
+        let mut batcher = SpriteBatcher::new();
+        batcher.push(B, DrawOrder::default(), raw(0));
+        batcher.push(A, DrawOrder::default(), raw(1));
+        batcher.push(B, DrawOrder::default(), raw(2));
+        batcher.push(A, DrawOrder::default(), raw(3));
+        batcher.prepare();
+        assert_eq!(ids(&batcher), [1, 3, 0, 2]);
+        assert_eq!(batcher.batches(), [
+            DrawBatch { key: A, instances: 0..2, indices: None },
+            DrawBatch { key: B, instances: 2..4, indices: None },
+        ]);
+    }
+
+    #[test]
+    fn shapes_are_sorted_between_sprites_and_never_merged() {
+        let shapes = BatchKey { mesh: MeshHandle::SHAPES, ..A };
+        let mut batcher = SpriteBatcher::new();
+        batcher.push(A, DrawOrder::new(RenderLayer::UI, 1.0), raw(0));
+        batcher.push_shapes(shapes, DrawOrder::new(RenderLayer::UI, 0.0), 0..6);
+        batcher.push_shapes(shapes, DrawOrder::new(RenderLayer::UI, 0.0), 6..9);
+        batcher.push(A, DrawOrder::new(RenderLayer::UI, -1.0), raw(1));
+        batcher.prepare();
+        assert_eq!(batcher.batches(), [
+            DrawBatch { key: A, instances: 0..1, indices: None },
+            DrawBatch { key: shapes, instances: 1..2, indices: Some(0..6) },
+            DrawBatch { key: shapes, instances: 2..3, indices: Some(6..9) },
+            DrawBatch { key: A, instances: 3..4, indices: None },
+        ]);
+        assert_eq!(batcher.instances()[1].model, RenderableInstanceRaw::IDENTITY.model);
+    }
+
+    #[test]
+    fn only_adjacent_equal_keys_are_merged() {
+        let mut batcher = SpriteBatcher::new();
+        batcher.push(A, DrawOrder::new(RenderLayer::World, 0.0), raw(0));
+        batcher.push(A, DrawOrder::new(RenderLayer::World, 1.0), raw(1));
+        batcher.push(B, DrawOrder::new(RenderLayer::World, 2.0), raw(2));
+        batcher.push(A, DrawOrder::new(RenderLayer::World, 3.0), raw(3));
+        batcher.prepare();
+        assert_eq!(batcher.batches(), [
+            DrawBatch { key: A, instances: 0..2, indices: None },
+            DrawBatch { key: B, instances: 2..3, indices: None },
+            DrawBatch { key: A, instances: 3..4, indices: None },
+        ]);
+
+        // Clearing keeps the prepared frame until the next prepare.
+        batcher.clear();
+        assert!(batcher.is_empty());
+        assert_eq!(batcher.batches().len(), 3);
+        batcher.prepare();
+        assert!(batcher.batches().is_empty() && batcher.instances().is_empty());
+    }
+}
@@ -0,0 +1,51 @@
+use wgpu::{BlendComponent, BlendFactor, BlendOperation, BlendState};
+
+// How a sprite is combined with what is already on screen.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum BlendMode {
+    // Overwrites the destination, alpha is ignored.
+    Opaque,
+    #[default]
+    Alpha,
+    // For textures and tints whose colour is already multiplied by their alpha.
+    Premultiplied,
+    // Adds onto the destination, for glows and particles.
+    Additive,
+    // Multiplies the destination by the source colour, for shadows and tinting overlays.
+    // Alpha is ignored, so parts that should leave the destination alone have to be white.
+    Multiply,
+}
+
+impl BlendMode {
+    pub fn blend_state(&self) -> BlendState {
+        match self {
+            BlendMode::Opaque => BlendState::REPLACE,
+            BlendMode::Alpha => BlendState::ALPHA_BLENDING,
+            BlendMode::Premultiplied => BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+            BlendMode::Additive => BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::SrcAlpha,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+                alpha: BlendComponent {
+                    src_factor: BlendFactor::Zero,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+            },
+            BlendMode::Multiply => BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::Zero,
+                    dst_factor: BlendFactor::Src,
+                    operation: BlendOperation::Add,
+                },
+                alpha: BlendComponent {
+                    src_factor: BlendFactor::Zero,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+            },
+        }
+    }
+}
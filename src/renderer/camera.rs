@@ -0,0 +1,91 @@
+use cgmath::{Matrix4, Rad, SquareMatrix, Vector2, Vector3, Vector4};
+
+// World space has y pointing up and, at zoom 1.0, one unit per physical pixel.
+// Screen space is in physical pixels with (0, 0) at the top left of the window.
+#[derive(Copy, Clone, Debug)]
+pub struct Camera2D {
+    // World position shown at the centre of the viewport.
+    pub position: Vector2<f32>,
+    pub zoom: f32,
+    // Counter-clockwise, in radians.
+    pub rotation: f32,
+    pub viewport: Vector2<f32>,
+}
+
+impl Camera2D {
+    pub fn new(viewport_width: u32, viewport_height: u32) -> Self {
+        Camera2D {
+            position: Vector2::new(0.0, 0.0),
+            zoom: 1.0,
+            rotation: 0.0,
+            viewport: Vector2::new(viewport_width as f32, viewport_height as f32),
+        }
+    }
+
+    pub fn view_matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_scale(self.zoom)
+            * Matrix4::from_angle_z(Rad(-self.rotation))
+            * Matrix4::from_translation(Vector3::new(-self.position.x, -self.position.y, 0.0))
+    }
+
+    pub fn projection_matrix(&self) -> Matrix4<f32> {
+        let half_width = self.viewport.x.max(1.0) / 2.0;
+        let half_height = self.viewport.y.max(1.0) / 2.0;
+        cgmath::ortho(-half_width, half_width, -half_height, half_height, -1.0, 1.0)
+    }
+
+    pub fn view_projection(&self) -> Matrix4<f32> {
+        self.projection_matrix() * self.view_matrix()
+    }
+
+    pub fn screen_to_world(&self, screen: Vector2<f32>) -> Vector2<f32> {
+        let ndc = Vector4::new(
+            screen.x / self.viewport.x * 2.0 - 1.0,
+            1.0 - screen.y / self.viewport.y * 2.0,
+            0.0,
+            1.0,
+        );
+        let world = self.view_projection().invert().unwrap_or_else(Matrix4::identity) * ndc;
+        Vector2::new(world.x, world.y)
+    }
+
+    pub fn world_to_screen(&self, world: Vector2<f32>) -> Vector2<f32> {
+        let ndc = self.view_projection() * Vector4::new(world.x, world.y, 0.0, 1.0);
+        Vector2::new(
+            (ndc.x + 1.0) / 2.0 * self.viewport.x,
+            (1.0 - ndc.y) / 2.0 * self.viewport.y,
+        )
+    }
+
+    pub fn to_uniform(&self) -> CameraUniform {
+        CameraUniform {
+            view_projection: self.view_projection().into(),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CameraUniform {
+    view_projection: [[f32; 4]; 4],
+}
+
+impl CameraUniform {
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Camera Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        })
+    }
+}
@@ -0,0 +1,65 @@
+use std::marker::PhantomData;
+
+use wgpu::{Buffer, BufferUsages, Device, Queue};
+
+// GPU buffer that is rewritten every frame and only reallocated when the data outgrows it.
+pub struct DynamicBuffer<T: bytemuck::Pod> {
+    buffer: Buffer,
+    usage: BufferUsages,
+    label: &'static str,
+    capacity: usize,
+    len: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T: bytemuck::Pod> DynamicBuffer<T> {
+    pub fn new(device: &Device, usage: BufferUsages, capacity: usize, label: &'static str) -> Self {
+        let capacity = capacity.max(1);
+        DynamicBuffer {
+            buffer: Self::create_buffer(device, usage, capacity, label),
+            usage: usage | BufferUsages::COPY_DST,
+            label,
+            capacity,
+            len: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    fn create_buffer(device: &Device, usage: BufferUsages, capacity: usize, label: &'static str) -> Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: (capacity * std::mem::size_of::<T>()) as wgpu::BufferAddress,
+            usage: usage | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    // Uploads `data`, growing to the next power of two if it does not fit.
+    pub fn write(&mut self, device: &Device, queue: &Queue, data: &[T]) {
+        if data.len() > self.capacity {
+            self.capacity = data.len().next_power_of_two();
+            self.buffer = Self::create_buffer(device, self.usage, self.capacity, self.label);
+        }
+        self.len = data.len();
+        if !data.is_empty() {
+            queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(data));
+        }
+    }
+
+    // Slice covering only the data written by the last call to write.
+    pub fn slice(&self) -> wgpu::BufferSlice<'_> {
+        self.buffer.slice(..(self.len.max(1) * std::mem::size_of::<T>()) as wgpu::BufferAddress)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
@@ -0,0 +1,64 @@
+use std::{path::{Path, PathBuf}, time::{Duration, Instant, SystemTime}};
+
+use super::material::MaterialHandle;
+
+// Checking every file on every frame is wasteful, edits are never that quick anyway.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum ReloadTarget {
+    // The vertex stage every material is built on top of.
+    SpriteVertex,
+    Material(MaterialHandle),
+}
+
+struct WatchedFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    target: ReloadTarget,
+}
+
+// Polls shader files for modifications, only used when RenderConfig::hot_reload_shaders is set.
+pub(crate) struct ShaderWatcher {
+    files: Vec<WatchedFile>,
+    last_poll: Instant,
+}
+
+impl ShaderWatcher {
+    pub fn new() -> Self {
+        ShaderWatcher {
+            files: Vec::new(),
+            last_poll: Instant::now(),
+        }
+    }
+
+    fn modified(path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+    }
+
+    pub fn watch(&mut self, path: &Path, target: ReloadTarget) {
+        self.files.push(WatchedFile {
+            path: path.to_path_buf(),
+            modified: Self::modified(path),
+            target,
+        });
+    }
+
+    // Everything whose file changed since the last poll.
+    pub fn poll(&mut self) -> Vec<ReloadTarget> {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return Vec::new();
+        }
+        self.last_poll = Instant::now();
+        let mut changed = Vec::new();
+        for file in &mut self.files {
+            let modified = Self::modified(&file.path);
+            // A missing file is most likely an editor saving through a rename, wait for it to come back.
+            if modified.is_some() && modified != file.modified {
+                file.modified = modified;
+                changed.push(file.target);
+            }
+        }
+        changed
+    }
+}
@@ -0,0 +1,177 @@
+use std::{num::NonZeroU64, path::{Path, PathBuf}};
+
+use wgpu::{BindGroup, BindGroupLayout, Buffer, Device, PipelineLayout, ShaderModule, util::DeviceExt};
+
+use super::texture::{Texture, TextureHandle};
+use crate::error::{RueError, RueResult};
+
+// Vertex stage shared by every material, the material source only has to add the fragment stage.
+pub const SPRITE_VERTEX_SOURCE: &str = include_str!("sprite_vertex.wgsl");
+pub const BASE_FRAGMENT_SOURCE: &str = include_str!("base_shader.wgsl");
+
+// Identifies a registered material, the default handle draws with base_shader.wgsl.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MaterialHandle(pub(crate) usize);
+
+// A fragment shader plus the resources it reads. The source is appended to sprite_vertex.wgsl and has to define
+// `fragment_main(in: VertexOutput) -> [[location(0)]] vec4<f32>`. Besides the sprite texture in group 1 it can use group 2:
+// binding 0 holds the uniforms, if any, and extra texture i is at binding 1 + 2 * i with its sampler at binding 2 + 2 * i.
+#[derive(Clone, Debug)]
+pub struct MaterialDescriptor {
+    pub label: String,
+    pub source: String,
+    pub uniforms: Option<Vec<u8>>,
+    pub textures: Vec<TextureHandle>,
+    // File the source was read from, watched for changes when shader hot reloading is enabled.
+    pub path: Option<PathBuf>,
+}
+
+impl MaterialDescriptor {
+    pub fn new(label: &str, source: &str) -> Self {
+        MaterialDescriptor {
+            label: String::from(label),
+            source: String::from(source),
+            uniforms: None,
+            textures: Vec::new(),
+            path: None,
+        }
+    }
+
+    pub fn from_file(label: &str, path: impl AsRef<Path>) -> RueResult<Self> {
+        let path = path.as_ref();
+        let mut descriptor = MaterialDescriptor::new(label, &read_shader(path)?);
+        descriptor.path = Some(path.to_path_buf());
+        Ok(descriptor)
+    }
+
+    // Initial value of the uniform block, its size is fixed from here on.
+    pub fn with_uniforms<U: bytemuck::Pod>(mut self, uniforms: &U) -> Self {
+        self.uniforms = Some(bytemuck::bytes_of(uniforms).to_vec());
+        self
+    }
+
+    pub fn with_texture(mut self, texture: TextureHandle) -> Self {
+        self.textures.push(texture);
+        self
+    }
+}
+
+pub(crate) fn read_shader(path: &Path) -> RueResult<String> {
+    std::fs::read_to_string(path).map_err(|e| RueError::Asset {
+        path: path.display().to_string(),
+        message: e.to_string(),
+    })
+}
+
+pub(crate) struct Material {
+    pub descriptor: MaterialDescriptor,
+    pub shader: ShaderModule,
+    pub pipeline_layout: PipelineLayout,
+    pub bind_group: Option<BindGroup>,
+    pub uniform_buffer: Option<Buffer>,
+    // Padded size of the uniform buffer in bytes.
+    pub uniform_size: wgpu::BufferAddress,
+}
+
+impl Material {
+    // `vertex_source` is normally SPRITE_VERTEX_SOURCE, `base_layouts` the camera and sprite texture layouts every material shares.
+    pub fn new(device: &Device, vertex_source: &str, base_layouts: &[&BindGroupLayout], textures: &[Texture], descriptor: MaterialDescriptor) -> Self {
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some(&descriptor.label),
+            source: wgpu::ShaderSource::Wgsl(format!("{}\n{}", vertex_source, descriptor.source).into()),
+        });
+
+        // Uniform buffers have to be a multiple of 16 bytes.
+        let uniform_size = descriptor.uniforms.as_ref().map_or(0, |uniforms| uniforms.len().div_ceil(16).max(1) * 16);
+        let uniform_buffer = descriptor.uniforms.as_ref().map(|uniforms| {
+            let mut contents = uniforms.clone();
+            contents.resize(uniform_size, 0);
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&descriptor.label),
+                contents: &contents,
+                // COPY_SRC so the current values survive a hot reload.
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+            })
+        });
+
+        let mut layout_entries = Vec::new();
+        let mut entries = Vec::new();
+        let uniform_size = uniform_size as wgpu::BufferAddress;
+        if let Some(buffer) = &uniform_buffer {
+            layout_entries.push(wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: NonZeroU64::new(uniform_size),
+                },
+                count: None,
+            });
+            entries.push(wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            });
+        }
+        for (i, handle) in descriptor.textures.iter().enumerate() {
+            let texture = &textures[handle.0];
+            let binding = 1 + 2 * i as u32;
+            layout_entries.push(wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            });
+            layout_entries.push(wgpu::BindGroupLayoutEntry {
+                binding: binding + 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            });
+            entries.push(wgpu::BindGroupEntry {
+                binding,
+                resource: wgpu::BindingResource::TextureView(&texture.view),
+            });
+            entries.push(wgpu::BindGroupEntry {
+                binding: binding + 1,
+                resource: wgpu::BindingResource::Sampler(&texture.sampler),
+            });
+        }
+
+        let material_layout;
+        let mut bind_group_layouts = base_layouts.to_vec();
+        let bind_group = if layout_entries.is_empty() {
+            None
+        }
+        else {
+            material_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some(&descriptor.label),
+                entries: &layout_entries,
+            });
+            bind_group_layouts.push(&material_layout);
+            Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(&descriptor.label),
+                layout: &material_layout,
+                entries: &entries,
+            }))
+        };
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(&descriptor.label),
+            bind_group_layouts: &bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+
+        Material {
+            descriptor,
+            shader,
+            pipeline_layout,
+            bind_group,
+            uniform_buffer,
+            uniform_size,
+        }
+    }
+}
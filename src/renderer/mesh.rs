@@ -0,0 +1,44 @@
+use wgpu::{Buffer, Device, util::DeviceExt};
+
+use super::Vertex;
+
+// Identifies geometry uploaded with Renderer::create_mesh.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MeshHandle(pub(crate) usize);
+
+impl MeshHandle {
+    // White unit quad with texture coordinates, what sprites are drawn with.
+    pub const QUAD: MeshHandle = MeshHandle(0);
+    // Vertex coloured unit square used by plain RenderableInstances.
+    pub const SQUARE: MeshHandle = MeshHandle(1);
+    // Stands for the ShapeBatch buffers in batch keys, it is not a registered mesh.
+    pub(crate) const SHAPES: MeshHandle = MeshHandle(usize::MAX);
+}
+
+pub struct Mesh {
+    pub vertex_buffer: Buffer,
+    pub index_buffer: Buffer,
+    pub num_vertices: u32,
+    pub num_indices: u32,
+}
+
+impl Mesh {
+    pub fn new(device: &Device, vertices: &[Vertex], indices: &[u16], label: &str) -> Self {
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{} Vertex Buffer", label)),
+            contents: bytemuck::cast_slice(vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{} Index Buffer", label)),
+            contents: bytemuck::cast_slice(indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        Mesh {
+            vertex_buffer,
+            index_buffer,
+            num_vertices: vertices.len() as u32,
+            num_indices: indices.len() as u32,
+        }
+    }
+}
@@ -0,0 +1,1199 @@
+pub mod texture;
+pub mod batch;
+pub mod camera;
+pub mod dynamic_buffer;
+pub mod blend;
+pub mod material;
+pub mod mesh;
+pub mod shapes;
+pub mod text;
+mod hot_reload;
+
+use std::{rc::Rc, cell::RefCell, collections::HashMap, path::{Path, PathBuf}, num::NonZeroU32, time::{SystemTime, UNIX_EPOCH}};
+
+use cgmath::{Matrix3, Vector2};
+use wgpu::{Surface, Queue, SurfaceConfiguration, Device, RenderPipeline, util::DeviceExt, Buffer, BufferUsages, TextureView};
+use winit::{window::Window, dpi::PhysicalSize};
+
+use crate::helpers::{colors::Color, self};
+use crate::error::{RueError, RueResult};
+use crate::math::{Transform2D, affine_to_matrix4};
+use texture::{Texture, TextureHandle};
+use batch::{BatchKey, DrawOrder, RenderLayer, SpriteBatcher};
+use camera::{Camera2D, CameraUniform};
+use dynamic_buffer::DynamicBuffer;
+use blend::BlendMode;
+use material::{Material, MaterialDescriptor, MaterialHandle, BASE_FRAGMENT_SOURCE, SPRITE_VERTEX_SOURCE, read_shader};
+use hot_reload::{ShaderWatcher, ReloadTarget};
+use mesh::{Mesh, MeshHandle};
+use shapes::ShapeBatch;
+use text::{Font, FontHandle, TextLayout, TextStyle};
+use crate::actors::sprite::Sprite;
+
+pub struct RenderConfig {
+    pub clear_color: Color,
+    // None lets Game choose from its frame pacing and means Fifo otherwise. wgpu 0.12 cannot tell which modes a
+    // surface supports and quietly presents with Fifo instead of an unsupported one, so only Fifo is guaranteed.
+    pub present_mode: Option<wgpu::PresentMode>,
+    // MSAA samples per pixel, 1 or 4 since those are the only counts every adapter supports. 2 and 8 are drawn with 4.
+    pub sample_count: u32,
+    // Skip hardware adapters and go straight to the software fallback.
+    pub force_fallback_adapter: bool,
+    // Development option: rebuild any material whose file changes, and the engine shaders too when shader_dir is set.
+    pub hot_reload_shaders: bool,
+    // Folder with editable copies of sprite_vertex.wgsl and base_shader.wgsl, relative paths start at the working directory.
+    pub shader_dir: Option<PathBuf>,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        RenderConfig {
+            clear_color: Color::default(),
+            present_mode: None,
+            sample_count: 1,
+            force_fallback_adapter: false,
+            hot_reload_shaders: false,
+            shader_dir: None,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Vertex {
+    position: [f32; 3],
+    pub color: [f32; 4],
+    pub tex_coords: [f32; 2],
+}
+
+impl Vertex {
+    pub const fn new(pos:[f32; 2], color: [f32; 3]) -> Self {
+        Vertex { position: [pos[0], pos[1], 0.0], color: [color[0], color[1], color[2], 1.0], tex_coords: [0.0, 0.0] }
+    }
+
+    pub const fn new_rgba(pos:[f32; 2], color: [f32; 4]) -> Self {
+        Vertex { position: [pos[0], pos[1], 0.0], color, tex_coords: [0.0, 0.0] }
+    }
+
+    pub const fn new_textured(pos:[f32; 2], color: [f32; 3], tex_coords: [f32; 2]) -> Self {
+        Vertex { position: [pos[0], pos[1], 0.0], color: [color[0], color[1], color[2], 1.0], tex_coords }
+    }
+
+    pub const fn new_with_rue_color(pos: [f32; 2], color: crate::helpers::colors::Color) -> Self {
+        Vertex {
+            position: [pos[0], pos[1], 0.0],
+            color: [color.r as f32, color.g as f32, color.b as f32, color.a as f32],
+            tex_coords: [0.0, 0.0],
+        }
+    }
+
+    pub fn get_position(&self) -> [f32; 2] {
+        [self.position[0], self.position[1]]
+    }
+
+    pub fn buffer_descriptor<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress, // Shader will skip this amount of bytes to get to the next vertex when iterating
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 7]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x2,
+                }
+            ],
+        }
+    }
+}
+
+pub const SQUARE_VERTICES: &[Vertex] = &[
+    Vertex::new([-0.5, -0.5], [0.0, 0.0, 0.0]), // 0
+    Vertex::new([0.5, -0.5], [0.0, 5.0, 0.0]), // 1
+    Vertex::new([-0.5, 0.5], [0.0, 0.0, 1.0]), // 2
+    Vertex::new([0.5, 0.5], [1.0, 0.2, 0.5]), // 3
+];
+
+pub const SQUARE_INDICES: &[u16] = &[
+    0, 1, 2, 2, 1, 3
+];
+
+// White unit quad that sprites are drawn with, so only the texture and tint colour them.
+pub const QUAD_VERTICES: &[Vertex] = &[
+    Vertex::new_textured([-0.5, -0.5], [1.0, 1.0, 1.0], [0.0, 1.0]), // 0
+    Vertex::new_textured([0.5, -0.5], [1.0, 1.0, 1.0], [1.0, 1.0]), // 1
+    Vertex::new_textured([-0.5, 0.5], [1.0, 1.0, 1.0], [0.0, 0.0]), // 2
+    Vertex::new_textured([0.5, 0.5], [1.0, 1.0, 1.0], [1.0, 0.0]), // 3
+];
+
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct RenderableInstanceRaw {
+    pub model: [[f32; 4]; 4],
+    // Offset (xy) and size (zw) of the sampled region in normalised texture coordinates.
+    pub uv_rect: [f32; 4],
+    pub tint: [f32; 4],
+}
+
+impl RenderableInstanceRaw {
+    pub const FULL_UV_RECT: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+
+    // Draws geometry exactly where its vertices are, untinted.
+    pub const IDENTITY: RenderableInstanceRaw = RenderableInstanceRaw {
+        model: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ],
+        uv_rect: RenderableInstanceRaw::FULL_UV_RECT,
+        tint: [1.0, 1.0, 1.0, 1.0],
+    };
+
+    fn buffer_descriptor<'a>() -> wgpu::VertexBufferLayout<'a> {
+        use std::mem;
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<RenderableInstanceRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 16]>() as wgpu::BufferAddress,
+                    shader_location: 7,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 20]>() as wgpu::BufferAddress,
+                    shader_location: 8,
+                    format: wgpu::VertexFormat::Float32x4,
+                }
+            ],
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    pub draw_calls: u32,
+    pub instances: u32,
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct RenderableInstance {
+    pub transform: Transform2D,
+    // World matrix of whatever this is attached to, the transform is relative to it.
+    pub parent: Option<Matrix3<f32>>,
+    // Sorted together with sprites. The y used on y-sorted layers comes from the transform.
+    pub order: DrawOrder,
+}
+
+impl RenderableInstance {
+    pub fn with_parent(mut self, parent: Matrix3<f32>) -> Self {
+        self.parent = Some(parent);
+        self
+    }
+
+    pub fn with_layer(mut self, layer: RenderLayer) -> Self {
+        self.order.layer = layer;
+        self
+    }
+
+    pub fn with_z(mut self, z: f32) -> Self {
+        self.order.z = z;
+        self
+    }
+
+    // Blends towards `other`, alpha 0.0 returns self and 1.0 returns other.
+    pub fn lerp(&self, other: &RenderableInstance, alpha: f32) -> RenderableInstance {
+        // Parents are not blended, interpolate the parent's own transform when it moves.
+        RenderableInstance {
+            transform: self.transform.lerp(&other.transform, alpha),
+            parent: other.parent,
+            order: other.order,
+        }
+    }
+
+    pub fn world_matrix(&self) -> Matrix3<f32> {
+        match self.parent {
+            Some(parent) => parent * self.transform.matrix(),
+            None => self.transform.matrix(),
+        }
+    }
+
+    pub fn to_raw(&self) -> RenderableInstanceRaw {
+        RenderableInstanceRaw {
+            model: affine_to_matrix4(&self.world_matrix()).into(),
+            uv_rect: RenderableInstanceRaw::FULL_UV_RECT,
+            tint: [1.0, 1.0, 1.0, 1.0],
+        }
+    }
+}
+
+impl From<Transform2D> for RenderableInstance {
+    fn from(transform: Transform2D) -> Self {
+        RenderableInstance {
+            transform,
+            parent: None,
+            order: DrawOrder::default(),
+        }
+    }
+}
+
+// Keeps the transforms of the last two fixed updates so drawing can blend between them.
+#[derive(Copy, Clone, Debug)]
+pub struct InterpolatedInstance {
+    pub previous: RenderableInstance,
+    pub current: RenderableInstance,
+}
+
+impl InterpolatedInstance {
+    pub fn new(instance: RenderableInstance) -> Self {
+        InterpolatedInstance {
+            previous: instance,
+            current: instance,
+        }
+    }
+
+    // Call once per fixed update with the new simulation state.
+    pub fn update(&mut self, instance: RenderableInstance) {
+        self.previous = self.current;
+        self.current = instance;
+    }
+
+    // Moves without blending, e.g. when respawning.
+    pub fn teleport(&mut self, instance: RenderableInstance) {
+        self.previous = instance;
+        self.current = instance;
+    }
+
+    pub fn blend(&self, alpha: f32) -> RenderableInstance {
+        self.previous.lerp(&self.current, alpha)
+    }
+}
+
+pub struct Renderer {
+    // Both None when rendering offscreen.
+    pub window: Option<Rc<Window>>,
+    surface: Option<Surface>,
+    offscreen_target: Option<wgpu::Texture>,
+    // Drawn into instead of the surface and resolved onto it when multisampling.
+    msaa_target: Option<wgpu::Texture>,
+    sample_count: u32,
+    rendering_device: Device,
+    render_queue: Queue,
+    surface_config: SurfaceConfiguration,
+    pub render_config: Rc<RefCell<RenderConfig>>,
+    window_size: PhysicalSize<u32>,
+    camera_bind_group_layout: wgpu::BindGroupLayout,
+    vertex_source: String,
+    shader_watcher: Option<ShaderWatcher>,
+    materials: Vec<Material>,
+    // One pipeline per material and blend mode, created the first time the pair is drawn with.
+    pipelines: HashMap<(MaterialHandle, BlendMode), RenderPipeline>,
+    meshes: Vec<Mesh>,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    textures: Vec<Texture>,
+    white_texture: TextureHandle,
+    pub batcher: SpriteBatcher,
+    stats: RenderStats,
+    pub camera: Camera2D,
+    camera_buffer: Buffer,
+    camera_bind_group: wgpu::BindGroup,
+    // The batched instances of the frame being drawn.
+    instance_data: Vec<RenderableInstanceRaw>,
+    instance_buffer: DynamicBuffer<RenderableInstanceRaw>,
+    pub shapes: ShapeBatch,
+    fonts: Vec<Font>,
+    shape_vertex_buffer: DynamicBuffer<Vertex>,
+    shape_index_buffer: DynamicBuffer<u32>,
+    // Index of the identity instance the shapes are drawn with.
+}
+
+impl Renderer {
+    async fn request_adapter(instance: &wgpu::Instance, surface: Option<&Surface>, force_fallback_adapter: bool) -> RueResult<wgpu::Adapter> {
+        let request = |force_fallback_adapter| instance.request_adapter(
+            &wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: surface,
+                force_fallback_adapter,
+            },
+        );
+        let adapter = match request(force_fallback_adapter).await {
+            Some(adapter) => adapter,
+            None if !force_fallback_adapter => {
+                log::warn!("No hardware graphics adapter found, falling back to a software adapter.");
+                request(true).await.ok_or(RueError::AdapterNotFound)?
+            },
+            None => return Err(RueError::AdapterNotFound),
+        };
+        log::info!("Using graphics adapter {:?}", adapter.get_info());
+        Ok(adapter)
+    }
+
+    async fn request_device(adapter: &wgpu::Adapter) -> RueResult<(Device, Queue)> {
+        Ok(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                features: wgpu::Features::empty(),
+                limits: wgpu::Limits::downlevel_defaults().using_resolution(adapter.limits()),
+                label: None,
+            }, None).await?)
+    }
+
+    pub async fn new(window: Rc<Window>, render_config:Option<Rc<RefCell<RenderConfig>>>) -> RueResult<Self> {
+        let r_config = render_config.unwrap_or_else(|| Rc::new(RefCell::new(RenderConfig::default())));
+        let window_size = window.inner_size();
+        let instance = wgpu::Instance::new(wgpu::Backends::all());
+        let surface = unsafe { instance.create_surface(&*window) };
+        let force_fallback_adapter = r_config.borrow().force_fallback_adapter;
+        let adapter = Self::request_adapter(&instance, Some(&surface), force_fallback_adapter).await?;
+        let (device, queue) = Self::request_device(&adapter).await?;
+        let present_mode = r_config.borrow().present_mode.unwrap_or(wgpu::PresentMode::Fifo);
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: surface.get_preferred_format(&adapter).ok_or(RueError::IncompatibleSurface)?,
+            width: window_size.width,
+            height: window_size.height,
+            present_mode,
+        };
+        Self::configure_surface(&device, &surface, &config);
+
+        Self::build(device, queue, config, Some(window), Some(surface), r_config).await
+    }
+
+    // Renders into an RGBA8 texture instead of a window, read it back with read_pixels.
+    // Works without any windowing system, e.g. on CI machines with only a software adapter.
+    pub async fn new_offscreen(width: u32, height: u32, render_config:Option<Rc<RefCell<RenderConfig>>>) -> RueResult<Self> {
+        let r_config = render_config.unwrap_or_else(|| Rc::new(RefCell::new(RenderConfig::default())));
+        let instance = wgpu::Instance::new(wgpu::Backends::all());
+        let force_fallback_adapter = r_config.borrow().force_fallback_adapter;
+        let adapter = Self::request_adapter(&instance, None, force_fallback_adapter).await?;
+        let (device, queue) = Self::request_device(&adapter).await?;
+        // Not used to configure a surface, but describes the offscreen target the same way.
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: Texture::FORMAT,
+            width: width.max(1),
+            height: height.max(1),
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        Self::build(device, queue, config, None, None, r_config).await
+    }
+
+    // wgpu 0.12 panics if the configuration is rejected, and replaces unsupported present modes with Fifo on its own.
+    fn configure_surface(device: &Device, surface: &Surface, config: &SurfaceConfiguration) {
+        if config.present_mode != wgpu::PresentMode::Fifo {
+            log::info!("Requested {:?} presentation, wgpu uses Fifo instead if the surface does not support it.", config.present_mode);
+        }
+        surface.configure(device, config);
+    }
+
+    fn create_offscreen_target(device: &Device, config: &SurfaceConfiguration) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Target"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: config.usage,
+        })
+    }
+
+    fn create_msaa_target(device: &Device, config: &SurfaceConfiguration, sample_count: u32) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("MSAA Target"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        })
+    }
+
+    fn create_pipeline(device: &Device, material: &Material, format: wgpu::TextureFormat, blend: BlendMode, sample_count: u32) -> RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(&material.descriptor.label),
+            layout: Some(&material.pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &material.shader,
+                entry_point: "vertex_main",
+                buffers: &[
+                    Vertex::buffer_descriptor(),
+                    RenderableInstanceRaw::buffer_descriptor(),
+                ],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &material.shader,
+                entry_point: "fragment_main",
+                targets: &[wgpu::ColorTargetState {
+                    format,
+                    blend: Some(blend.blend_state()),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }]
+            }),
+            primitive: wgpu::PrimitiveState { 
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None, front_face: wgpu::FrontFace::Ccw,
+                // Flipped sprites are mirrored quads, so both windings have to be drawn.
+                cull_mode: None,
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        })
+    }
+
+    // With hot reloading on, the engine's own shaders come from RenderConfig::shader_dir so they can be edited live.
+    fn engine_shaders(r_config: &RenderConfig) -> (String, MaterialDescriptor, Option<ShaderWatcher>) {
+        let embedded = MaterialDescriptor::new("Base Material", BASE_FRAGMENT_SOURCE);
+        if !r_config.hot_reload_shaders {
+            return (String::from(SPRITE_VERTEX_SOURCE), embedded, None);
+        }
+        let mut watcher = ShaderWatcher::new();
+        let shader_dir = match &r_config.shader_dir {
+            Some(shader_dir) => shader_dir,
+            None => {
+                log::warn!("RenderConfig::shader_dir is not set, hot reloading only custom materials.");
+                return (String::from(SPRITE_VERTEX_SOURCE), embedded, Some(watcher));
+            },
+        };
+        let vertex_path = shader_dir.join("sprite_vertex.wgsl");
+        let base_path = shader_dir.join("base_shader.wgsl");
+        match (read_shader(&vertex_path), MaterialDescriptor::from_file("Base Material", &base_path)) {
+            (Ok(vertex_source), Ok(base_descriptor)) => {
+                watcher.watch(&vertex_path, ReloadTarget::SpriteVertex);
+                watcher.watch(&base_path, ReloadTarget::Material(MaterialHandle::default()));
+                (vertex_source, base_descriptor, Some(watcher))
+            },
+            (Err(e), _) | (_, Err(e)) => {
+                log::warn!("{}, hot reloading only custom materials.", e);
+                (String::from(SPRITE_VERTEX_SOURCE), embedded, Some(watcher))
+            },
+        }
+    }
+
+    async fn build(device: Device, queue: Queue, config: SurfaceConfiguration, window: Option<Rc<Window>>, surface: Option<Surface>, r_config: Rc<RefCell<RenderConfig>>) -> RueResult<Self> {
+        let window_size = PhysicalSize::new(config.width, config.height);
+        let offscreen_target = match surface {
+            Some(_) => None,
+            None => Some(Self::create_offscreen_target(&device, &config)),
+        };
+
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let camera = Camera2D::new(window_size.width, window_size.height);
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::cast_slice(&[camera.to_uniform()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let camera_bind_group_layout = CameraUniform::bind_group_layout(&device);
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Camera Bind Group"),
+            layout: &camera_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: camera_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let texture_bind_group_layout = Texture::bind_group_layout(&device);
+        let (vertex_source, base_descriptor, shader_watcher) = Self::engine_shaders(&r_config.borrow());
+        let base_material = Material::new(
+            &device,
+            &vertex_source,
+            &[&camera_bind_group_layout, &texture_bind_group_layout],
+            &[],
+            base_descriptor,
+        );
+        let mut pipelines = HashMap::new();
+        pipelines.insert((MaterialHandle::default(), BlendMode::default()), Self::create_pipeline(&device, &base_material, config.format, BlendMode::default(), 1));
+        if let Some(error) = device.pop_error_scope().await {
+            return Err(RueError::ShaderCompilation(error.to_string()));
+        }
+
+        // Order has to match MeshHandle::QUAD and MeshHandle::SQUARE.
+        let meshes = vec![
+            Mesh::new(&device, QUAD_VERTICES, SQUARE_INDICES, "Quad"),
+            Mesh::new(&device, SQUARE_VERTICES, SQUARE_INDICES, "Square"),
+        ];
+
+        // Untextured geometry samples this so everything can share one pipeline.
+        let white_texture = Texture::from_rgba8(&device, &queue, &texture_bind_group_layout, &[255, 255, 255, 255], 1, 1, Some("White Texture"));
+
+        let instance_buffer = DynamicBuffer::new(&device, BufferUsages::VERTEX, 1024, "Instance Buffer");
+        let shape_vertex_buffer = DynamicBuffer::new(&device, BufferUsages::VERTEX, 1024, "Shape Vertex Buffer");
+        let shape_index_buffer = DynamicBuffer::new(&device, BufferUsages::INDEX, 1024, "Shape Index Buffer");
+
+        let sample_count = r_config.borrow().sample_count;
+        let mut renderer = Renderer {
+            window,
+            surface,
+            offscreen_target,
+            msaa_target: None,
+            sample_count: 1,
+            rendering_device: device,
+            render_queue: queue,
+            surface_config: config,
+            render_config: r_config,
+            window_size,
+            camera_bind_group_layout,
+            vertex_source,
+            shader_watcher,
+            materials: vec![base_material],
+            pipelines,
+            meshes,
+            texture_bind_group_layout,
+            textures: vec![white_texture],
+            white_texture: TextureHandle(0),
+            batcher: SpriteBatcher::new(),
+            stats: RenderStats::default(),
+            camera,
+            camera_buffer,
+            camera_bind_group,
+            instance_data: Vec::new(),
+            instance_buffer,
+            shapes: ShapeBatch::new(),
+            fonts: Vec::new(),
+            shape_vertex_buffer,
+            shape_index_buffer,
+        };
+        if sample_count != 1 {
+            renderer.set_sample_count(sample_count)?;
+        }
+        Ok(renderer)
+    }
+
+    fn add_texture(&mut self, texture: Texture) -> TextureHandle {
+        self.textures.push(texture);
+        TextureHandle(self.textures.len() - 1)
+    }
+
+    // Decodes an encoded image (PNG, JPEG, ...) and uploads it to the GPU.
+    pub fn load_texture(&mut self, bytes: &[u8], label: &str) -> RueResult<TextureHandle> {
+        let texture = Texture::from_bytes(&self.rendering_device, &self.render_queue, &self.texture_bind_group_layout, bytes, label)?;
+        Ok(self.add_texture(texture))
+    }
+
+    pub fn load_texture_file(&mut self, path: impl AsRef<Path>) -> RueResult<TextureHandle> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|e| RueError::Asset {
+            path: path.display().to_string(),
+            message: e.to_string(),
+        })?;
+        self.load_texture(&bytes, &path.display().to_string())
+    }
+
+    pub fn create_texture(&mut self, pixels: &[u8], width: u32, height: u32) -> TextureHandle {
+        let texture = Texture::from_rgba8(&self.rendering_device, &self.render_queue, &self.texture_bind_group_layout, pixels, width, height, None);
+        self.add_texture(texture)
+    }
+
+    pub fn white_texture(&self) -> TextureHandle {
+        self.white_texture
+    }
+
+    pub fn texture(&self, handle: TextureHandle) -> &Texture {
+        &self.textures[handle.0]
+    }
+
+    pub fn texture_size(&self, handle: TextureHandle) -> (u32, u32) {
+        let texture = self.texture(handle);
+        (texture.width, texture.height)
+    }
+
+    // Builds the material together with its default pipeline, so shader errors show up here rather than mid-frame.
+    fn compile_material(&self, descriptor: MaterialDescriptor) -> RueResult<(Material, RenderPipeline)> {
+        let device = &self.rendering_device;
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let material = Material::new(device, &self.vertex_source, &[&self.camera_bind_group_layout, &self.texture_bind_group_layout], &self.textures, descriptor);
+        let pipeline = Self::create_pipeline(device, &material, self.surface_config.format, BlendMode::default(), self.sample_count);
+        match pollster::block_on(device.pop_error_scope()) {
+            Some(error) => Err(RueError::ShaderCompilation(format!("{}: {}", material.descriptor.label, error))),
+            None => Ok((material, pipeline)),
+        }
+    }
+
+    // Compiles the material's shader and checks it against its resources, the returned handle can be put on sprites.
+    pub fn create_material(&mut self, descriptor: MaterialDescriptor) -> RueResult<MaterialHandle> {
+        let (material, pipeline) = self.compile_material(descriptor)?;
+        let handle = MaterialHandle(self.materials.len());
+        if let (Some(watcher), Some(path)) = (self.shader_watcher.as_mut(), &material.descriptor.path) {
+            watcher.watch(path, ReloadTarget::Material(handle));
+        }
+        self.materials.push(material);
+        self.pipelines.insert((handle, BlendMode::default()), pipeline);
+        Ok(handle)
+    }
+
+    // Rebuilds a material from its file, keeping its current uniform values. On failure the old version stays in use.
+    pub fn reload_material(&mut self, handle: MaterialHandle) -> RueResult<()> {
+        let mut descriptor = self.materials[handle.0].descriptor.clone();
+        if let Some(path) = &descriptor.path {
+            descriptor.source = read_shader(path)?;
+        }
+        let (material, pipeline) = self.compile_material(descriptor)?;
+        if let (Some(old_buffer), Some(new_buffer)) = (&self.materials[handle.0].uniform_buffer, &material.uniform_buffer) {
+            let mut encoder = self.rendering_device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Material Reload Encoder"),
+            });
+            encoder.copy_buffer_to_buffer(old_buffer, 0, new_buffer, 0, material.uniform_size);
+            self.render_queue.submit(std::iter::once(encoder.finish()));
+        }
+        self.pipelines.retain(|(pipeline_material, _), _| *pipeline_material != handle);
+        self.pipelines.insert((handle, BlendMode::default()), pipeline);
+        self.materials[handle.0] = material;
+        Ok(())
+    }
+
+    fn reload_changed_shaders(&mut self) {
+        let changed = match self.shader_watcher.as_mut() {
+            Some(watcher) => watcher.poll(),
+            None => return,
+        };
+        let materials: Vec<MaterialHandle> = if changed.contains(&ReloadTarget::SpriteVertex) {
+            // Only watched when shader_dir is set.
+            let path = match &self.render_config.borrow().shader_dir {
+                Some(shader_dir) => shader_dir.join("sprite_vertex.wgsl"),
+                None => return,
+            };
+            let source = match read_shader(&path) {
+                Ok(source) => source,
+                Err(e) => {
+                    log::error!("{}", e);
+                    return;
+                },
+            };
+            // Check the new vertex stage against the base material before adopting it, a broken one would
+            // otherwise make every later material fail to compile until the file is fixed.
+            let last_good = std::mem::replace(&mut self.vertex_source, source);
+            if let Err(e) = self.compile_material(self.materials[0].descriptor.clone()) {
+                self.vertex_source = last_good;
+                log::error!("{}, keeping the last working vertex shader.", e);
+                return;
+            }
+            (0..self.materials.len()).map(MaterialHandle).collect()
+        }
+        else {
+            changed.iter().filter_map(|target| match target {
+                ReloadTarget::Material(handle) => Some(*handle),
+                ReloadTarget::SpriteVertex => None,
+            }).collect()
+        };
+        for handle in materials {
+            match self.reload_material(handle) {
+                Ok(()) => log::info!("Reloaded shader for {}", self.materials[handle.0].descriptor.label),
+                Err(e) => log::error!("{}, keeping the last working version.", e),
+            }
+        }
+    }
+
+    pub fn default_material(&self) -> MaterialHandle {
+        MaterialHandle::default()
+    }
+
+    // Overwrites the material's uniform block, `uniforms` must be the type it was created with.
+    pub fn set_material_uniforms<U: bytemuck::Pod>(&mut self, material: MaterialHandle, uniforms: &U) -> RueResult<()> {
+        let material = &self.materials[material.0];
+        let bytes = bytemuck::bytes_of(uniforms);
+        match &material.uniform_buffer {
+            Some(buffer) if bytes.len() as wgpu::BufferAddress <= material.uniform_size => {
+                self.render_queue.write_buffer(buffer, 0, bytes);
+                Ok(())
+            },
+            Some(_) => Err(RueError::Material(format!("{}: uniforms are {} bytes, larger than the block it was created with", material.descriptor.label, bytes.len()))),
+            None => Err(RueError::Material(format!("{}: was created without uniforms", material.descriptor.label))),
+        }
+    }
+
+    // Uploads geometry once, draw it by putting the handle in a RenderData's batch key.
+    pub fn create_mesh(&mut self, vertices: &[Vertex], indices: &[u16], label: &str) -> MeshHandle {
+        self.meshes.push(Mesh::new(&self.rendering_device, vertices, indices, label));
+        MeshHandle(self.meshes.len() - 1)
+    }
+
+    pub fn mesh(&self, handle: MeshHandle) -> &Mesh {
+        &self.meshes[handle.0]
+    }
+
+    // Parses a TTF or OTF font, glyphs are rasterised into its atlas as they are first drawn.
+    pub fn load_font(&mut self, bytes: &[u8], label: &str) -> RueResult<FontHandle> {
+        let atlas = self.create_texture(&vec![0; (text::ATLAS_SIZE * text::ATLAS_SIZE * 4) as usize], text::ATLAS_SIZE, text::ATLAS_SIZE);
+        self.fonts.push(Font::from_bytes(bytes, label, atlas)?);
+        Ok(FontHandle(self.fonts.len() - 1))
+    }
+
+    pub fn load_font_file(&mut self, path: impl AsRef<Path>) -> RueResult<FontHandle> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|e| RueError::Asset {
+            path: path.display().to_string(),
+            message: e.to_string(),
+        })?;
+        self.load_font(&bytes, &path.display().to_string())
+    }
+
+    // Lays the text out without drawing it, e.g. to measure it.
+    pub fn layout_text(&self, font: FontHandle, text: &str, style: &TextStyle) -> TextLayout {
+        self.fonts[font.0].layout(text, style)
+    }
+
+    // Draws `text` with its top left corner at `position` in world space.
+    pub fn draw_text(&mut self, font: FontHandle, text: &str, position: Vector2<f32>, style: &TextStyle) {
+        let font = &mut self.fonts[font.0];
+        let layout = font.layout(text, style);
+        let key = BatchKey::new(font.atlas);
+        let order = DrawOrder::new(style.layer, style.z);
+        for instance in font.glyph_instances(&self.textures[font.atlas.0], &self.render_queue, &layout, position, style) {
+            self.batcher.push(key, order, instance);
+        }
+    }
+
+    // Draws the untextured unit square, sorted with everything else by the instance's draw order.
+    pub fn submit_instance(&mut self, instance: &RenderableInstance) {
+        let key = BatchKey {
+            mesh: MeshHandle::SQUARE,
+            ..BatchKey::new(self.white_texture)
+        };
+        let raw = instance.to_raw();
+        let order = DrawOrder {
+            // Translation part of the model matrix, where the instance ends up in the world.
+            y: raw.model[3][1],
+            ..instance.order
+        };
+        self.batcher.push(key, order, raw);
+    }
+
+    pub fn submit(&mut self, data: RenderData) {
+        self.batcher.push(data.key, data.order, data.instance);
+    }
+
+    // The mode the surface was configured with. Anything but Fifo may have been replaced by Fifo without notice.
+    pub fn requested_present_mode(&self) -> wgpu::PresentMode {
+        self.surface_config.present_mode
+    }
+
+    // Reconfigures the surface, e.g. from an options menu. Offscreen renderers only remember the mode.
+    pub fn set_present_mode(&mut self, present_mode: wgpu::PresentMode) {
+        self.render_config.borrow_mut().present_mode = Some(present_mode);
+        self.surface_config.present_mode = present_mode;
+        if let Some(surface) = &self.surface {
+            Self::configure_surface(&self.rendering_device, surface, &self.surface_config);
+        }
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    // Switches multisampling and rebuilds every pipeline, returning the sample count actually in use.
+    pub fn set_sample_count(&mut self, sample_count: u32) -> RueResult<u32> {
+        // wgpu 0.12 has no way to ask which counts an adapter supports and only rejects counts that are not a power of
+        // two, so anything else would fail in the backend. WebGPU guarantees 1 and 4, the other counts use 4 instead.
+        let chosen = match sample_count {
+            1 | 4 => sample_count,
+            2 | 8 => {
+                log::warn!("{}x multisampling cannot be checked for support, using 4x instead.", sample_count);
+                4
+            },
+            _ => return Err(RueError::InvalidSetting(format!("{} is not a valid sample count, use 1 or 4.", sample_count))),
+        };
+        self.render_config.borrow_mut().sample_count = chosen;
+        self.sample_count = chosen;
+        self.msaa_target = (chosen > 1).then(|| Self::create_msaa_target(&self.rendering_device, &self.surface_config, chosen));
+        self.pipelines.clear();
+        let default_key = (MaterialHandle::default(), BlendMode::default());
+        let pipeline = Self::create_pipeline(&self.rendering_device, &self.materials[default_key.0.0], self.surface_config.format, default_key.1, chosen);
+        self.pipelines.insert(default_key, pipeline);
+        Ok(chosen)
+    }
+
+    pub fn last_frame_stats(&self) -> RenderStats {
+        self.stats
+    }
+    
+    pub fn size(&self) -> PhysicalSize<u32> {
+        self.window_size
+    }
+
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        if new_size.width > 0 && new_size.height > 0 {
+            self.window_size = match &self.window {
+                Some(window) => window.inner_size(),
+                None => new_size,
+            };
+            self.surface_config.width = self.window_size.width;
+            self.surface_config.height = self.window_size.height;
+            match &self.surface {
+                Some(surface) => surface.configure(&self.rendering_device, &self.surface_config),
+                None => self.offscreen_target = Some(Self::create_offscreen_target(&self.rendering_device, &self.surface_config)),
+            }
+            if self.msaa_target.is_some() {
+                self.msaa_target = Some(Self::create_msaa_target(&self.rendering_device, &self.surface_config, self.sample_count));
+            }
+            self.camera.viewport = Vector2::new(self.window_size.width as f32, self.window_size.height as f32);
+        }
+    }
+
+    // Uploads the camera and every instance submitted this frame.
+    fn prepare_frame(&mut self, renderable_instances: &[RenderableInstance]) {
+        self.render_queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[self.camera.to_uniform()]));
+        for instance in renderable_instances {
+            self.submit_instance(instance);
+        }
+        let shape_key = BatchKey {
+            mesh: MeshHandle::SHAPES,
+            ..BatchKey::new(self.white_texture)
+        };
+        for (order, indices) in self.shapes.ranges() {
+            self.batcher.push_shapes(shape_key, *order, indices.clone());
+        }
+        self.batcher.prepare();
+        for batch in self.batcher.batches() {
+            let pipeline_key = (batch.key.material, batch.key.blend);
+            if !self.pipelines.contains_key(&pipeline_key) {
+                let pipeline = Self::create_pipeline(&self.rendering_device, &self.materials[batch.key.material.0], self.surface_config.format, batch.key.blend, self.sample_count);
+                self.pipelines.insert(pipeline_key, pipeline);
+            }
+        }
+        self.instance_data.extend_from_slice(self.batcher.instances());
+        self.instance_buffer.write(&self.rendering_device, &self.render_queue, &self.instance_data);
+        self.shape_vertex_buffer.write(&self.rendering_device, &self.render_queue, self.shapes.vertices());
+        self.shape_index_buffer.write(&self.rendering_device, &self.render_queue, self.shapes.indices());
+    }
+
+    // Records the prepared frame into `view`, can be called several times per frame.
+    fn encode_frame(&self, encoder: &mut wgpu::CommandEncoder, view: &TextureView) -> RenderStats {
+        let mut stats = RenderStats {
+            draw_calls: 0,
+            instances: self.instance_buffer.len() as u32,
+        };
+        let msaa_view = self.msaa_target.as_ref().map(|target| target.create_view(&wgpu::TextureViewDescriptor::default()));
+        let (view, resolve_target) = match &msaa_view {
+            Some(msaa_view) => (msaa_view, Some(view)),
+            None => (view, None),
+        };
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass"),
+            color_attachments: &[
+                wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(helpers::colors::color_to_wgpu_color(self.render_config.borrow().clear_color)),
+                        store: true,
+                    },
+                }
+            ],
+            depth_stencil_attachment: None,
+        });
+
+        let mut pipeline_key = (MaterialHandle::default(), BlendMode::default());
+        render_pass.set_pipeline(&self.pipelines[&pipeline_key]);
+        render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice());
+
+        let mut mesh_handle = None;
+        for batch in self.batcher.batches() {
+            if mesh_handle != Some(batch.key.mesh) {
+                mesh_handle = Some(batch.key.mesh);
+                if batch.key.mesh == MeshHandle::SHAPES {
+                    render_pass.set_vertex_buffer(0, self.shape_vertex_buffer.slice());
+                    render_pass.set_index_buffer(self.shape_index_buffer.slice(), wgpu::IndexFormat::Uint32);
+                }
+                else {
+                    let mesh = &self.meshes[batch.key.mesh.0];
+                    render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+                    render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+                }
+            }
+            if (batch.key.material, batch.key.blend) != pipeline_key {
+                if batch.key.material != pipeline_key.0 {
+                    if let Some(bind_group) = &self.materials[batch.key.material.0].bind_group {
+                        render_pass.set_bind_group(2, bind_group, &[]);
+                    }
+                }
+                pipeline_key = (batch.key.material, batch.key.blend);
+                render_pass.set_pipeline(&self.pipelines[&pipeline_key]);
+            }
+            render_pass.set_bind_group(1, &self.textures[batch.key.texture.0].bind_group, &[]);
+            let indices = match &batch.indices {
+                Some(indices) => indices.clone(),
+                None => 0..self.meshes[batch.key.mesh.0].num_indices,
+            };
+            render_pass.draw_indexed(indices, 0, batch.instances.clone());
+            stats.draw_calls += 1;
+        }
+        stats
+    }
+
+    // Clears the submissions but keeps what was uploaded, so the frame can still be captured.
+    fn end_frame(&mut self) {
+        self.instance_data.clear();
+        self.batcher.clear();
+        self.shapes.clear();
+    }
+
+    // Draws the given instances and everything submitted since the last frame.
+    pub fn render(&mut self, renderable_instances: &[RenderableInstance]) -> Result<(), wgpu::SurfaceError> {
+        self.reload_changed_shaders();
+        let output = match self.surface.as_ref().map(Surface::get_current_texture).transpose() {
+            Ok(output) => output,
+            Err(e) => {
+                self.end_frame();
+                return Err(e);
+            }
+        };
+        let view = match (&output, &self.offscreen_target) {
+            (Some(output), _) => output.texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            (None, Some(target)) => target.create_view(&wgpu::TextureViewDescriptor::default()),
+            (None, None) => unreachable!("Renderer has neither a surface nor an offscreen target."),
+        };
+        let mut encoder = self.rendering_device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Render Encoder"),
+        });
+        self.prepare_frame(renderable_instances);
+        self.stats = self.encode_frame(&mut encoder, &view);
+        self.render_queue.submit(std::iter::once(encoder.finish()));
+        self.end_frame();
+        if let Some(output) = output {
+            output.present();
+        }
+        Ok(())
+    }
+
+    // Copies a whole texture into tightly packed rows, blocking until the GPU is done.
+    fn read_texture(&self, texture: &wgpu::Texture, width: u32, height: u32) -> RueResult<Vec<u8>> {
+        let unpadded_bytes_per_row = 4 * width;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+        let buffer = self.rendering_device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = self.rendering_device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Readback Encoder"),
+        });
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(padded_bytes_per_row),
+                    rows_per_image: NonZeroU32::new(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.render_queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let mapping = slice.map_async(wgpu::MapMode::Read);
+        self.rendering_device.poll(wgpu::Maintain::Wait);
+        pollster::block_on(mapping).map_err(|e| RueError::Readback(e.to_string()))?;
+
+        // Rows are padded to COPY_BYTES_PER_ROW_ALIGNMENT, strip that back off.
+        let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+            }
+        }
+        buffer.unmap();
+        Ok(pixels)
+    }
+
+    // RGBA8 pixels of the last frame rendered offscreen, row by row from the top.
+    pub fn read_pixels(&self) -> RueResult<Vec<u8>> {
+        match &self.offscreen_target {
+            Some(target) => self.read_texture(target, self.surface_config.width, self.surface_config.height),
+            None => Err(RueError::Readback(String::from("Only offscreen renderers can be read back."))),
+        }
+    }
+
+    // RGBA8 pixels of the last rendered frame, row by row from the top.
+    // Surface textures cannot be copied from, so windowed renderers draw the frame again into a readable texture.
+    pub fn capture_pixels(&self) -> RueResult<Vec<u8>> {
+        if self.offscreen_target.is_some() {
+            return self.read_pixels();
+        }
+        let (width, height) = (self.surface_config.width, self.surface_config.height);
+        let target = self.rendering_device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Capture Target"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.surface_config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        });
+        let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self.rendering_device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Capture Encoder"),
+        });
+        self.encode_frame(&mut encoder, &view);
+        self.render_queue.submit(std::iter::once(encoder.finish()));
+        let mut pixels = self.read_texture(&target, width, height)?;
+        match self.surface_config.format {
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => {},
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => {
+                for pixel in pixels.chunks_exact_mut(4) {
+                    pixel.swap(0, 2);
+                }
+            },
+            format => return Err(RueError::Readback(format!("Capturing {:?} surfaces is not supported.", format))),
+        }
+        Ok(pixels)
+    }
+
+    // Writes the last rendered frame to `directory` as a PNG named after the current time, returning its path.
+    pub fn capture_frame(&self, directory: impl AsRef<Path>) -> RueResult<PathBuf> {
+        let directory = directory.as_ref();
+        let pixels = self.capture_pixels()?;
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let name = format!("screenshot_{}_{:03}", timestamp.as_secs(), timestamp.subsec_millis());
+        let mut path = directory.join(format!("{}.png", name));
+        let mut copy = 1;
+        while path.exists() {
+            path = directory.join(format!("{}_{}.png", name, copy));
+            copy += 1;
+        }
+        let screenshot_error = |message: String| RueError::Screenshot {
+            path: path.display().to_string(),
+            message,
+        };
+        std::fs::create_dir_all(directory).map_err(|e| screenshot_error(e.to_string()))?;
+        image::save_buffer(&path, &pixels, self.surface_config.width, self.surface_config.height, image::ColorType::Rgba8)
+            .map_err(|e| screenshot_error(e.to_string()))?;
+        Ok(path)
+    }
+}
+
+// Collects everything the game wants drawn this frame.
+pub struct RenderContext<'a> {
+    pub renderer: &'a mut Renderer,
+    // How far the simulation has progressed past the last fixed update, in fixed steps.
+    pub alpha: f32,
+}
+
+impl<'a> RenderContext<'a> {
+    pub fn new(renderer: &'a mut Renderer, alpha: f32) -> Self {
+        RenderContext {
+            renderer,
+            alpha: alpha.clamp(0.0, 1.0),
+        }
+    }
+
+    pub fn clear_color(&self) -> Color {
+        self.renderer.render_config.borrow().clear_color
+    }
+
+    pub fn set_clear_color(&mut self, color: Color) {
+        self.renderer.render_config.borrow_mut().clear_color = color;
+    }
+
+    pub fn draw(&mut self, instance: RenderableInstance) {
+        self.renderer.submit_instance(&instance);
+    }
+
+    pub fn draw_interpolated(&mut self, instance: &InterpolatedInstance) {
+        self.renderer.submit_instance(&instance.blend(self.alpha));
+    }
+
+    // Immediate mode rects, circles, polygons and lines, sorted with this frame's sprites by ShapeBatch::set_order.
+    pub fn shapes(&mut self) -> &mut ShapeBatch {
+        &mut self.renderer.shapes
+    }
+
+    pub fn draw_text(&mut self, font: FontHandle, text: &str, position: Vector2<f32>, style: &TextStyle) {
+        self.renderer.draw_text(font, text, position, style);
+    }
+
+    pub fn draw_renderable(&mut self, renderable: &(impl Renderable + ?Sized)) {
+        renderable.prepare_for_render(self.renderer);
+    }
+
+    pub fn draw_sprite(&mut self, sprite: &Sprite) {
+        self.draw_renderable(sprite);
+    }
+
+    pub fn finish(self) -> Result<(), wgpu::SurfaceError> {
+        self.renderer.render(&[])
+    }
+}
+
+// Renderable trait & impl
+// One instance of a mesh, the batch key says what it is drawn with.
+#[derive(Copy, Clone, Debug)]
+pub struct RenderData {
+    pub key: BatchKey,
+    pub order: DrawOrder,
+    pub instance: RenderableInstanceRaw,
+}
+
+// Anything that draws itself by submitting RenderData to the renderer once per frame.
+pub trait Renderable {
+    fn prepare_for_render(&self, renderer: &mut Renderer);
+}
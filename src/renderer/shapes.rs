@@ -0,0 +1,223 @@
+use std::ops::Range;
+
+use cgmath::{InnerSpace, Vector2};
+
+use super::{Vertex, batch::{DrawOrder, RenderLayer}};
+use crate::helpers::colors::Color;
+use crate::math::Rect;
+
+// Miters longer than this many half thicknesses are cut short, so very sharp corners do not spike out.
+const MITER_LIMIT: f32 = 4.0;
+
+// Immediate mode shapes in world space, rebuilt every frame and sorted with the sprites by the order set with set_order.
+// Rects have (x, y) at their bottom left corner since world space has y pointing up.
+#[derive(Default)]
+pub struct ShapeBatch {
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+    order: DrawOrder,
+    // Consecutive indices drawn with the same order, each one becomes a draw call.
+    ranges: Vec<(DrawOrder, Range<u32>)>,
+}
+
+impl ShapeBatch {
+    pub fn new() -> Self {
+        ShapeBatch::default()
+    }
+
+    pub fn vertices(&self) -> &[Vertex] {
+        &self.vertices
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    pub fn ranges(&self) -> &[(DrawOrder, Range<u32>)] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    // Also resets the order, so every frame starts out drawing on the default layer.
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+        self.ranges.clear();
+        self.order = DrawOrder::default();
+    }
+
+    // Layer and z of the shapes added from here on, e.g. RenderLayer::UI below the z of text for a panel behind it.
+    // Shapes are not y-sorted.
+    pub fn set_order(&mut self, layer: RenderLayer, z: f32) {
+        self.order = DrawOrder::new(layer, z);
+    }
+
+    pub fn order(&self) -> DrawOrder {
+        self.order
+    }
+
+    fn push_indices(&mut self, indices: &[u32]) {
+        let start = self.indices.len() as u32;
+        self.indices.extend_from_slice(indices);
+        let end = self.indices.len() as u32;
+        match self.ranges.last_mut() {
+            Some((order, range)) if *order == self.order => range.end = end,
+            _ => self.ranges.push((self.order, start..end)),
+        }
+    }
+
+    fn push_vertex(&mut self, position: Vector2<f32>, color: Color) -> u32 {
+        self.vertices.push(Vertex::new_with_rue_color([position.x, position.y], color));
+        self.vertices.len() as u32 - 1
+    }
+
+    fn push_quad(&mut self, corners: [Vector2<f32>; 4], color: Color) {
+        let first = self.push_vertex(corners[0], color);
+        for corner in &corners[1..] {
+            self.push_vertex(*corner, color);
+        }
+        self.push_indices(&[first, first + 1, first + 2, first, first + 2, first + 3]);
+    }
+
+    fn rect_corners(rect: Rect) -> [Vector2<f32>; 4] {
+        [
+            Vector2::new(rect.x, rect.y),
+            Vector2::new(rect.x + rect.width, rect.y),
+            Vector2::new(rect.x + rect.width, rect.y + rect.height),
+            Vector2::new(rect.x, rect.y + rect.height),
+        ]
+    }
+
+    fn ellipse_points(center: Vector2<f32>, radii: Vector2<f32>, segments: u32) -> Vec<Vector2<f32>> {
+        let segments = segments.max(3);
+        (0..segments).map(|i| {
+            let angle = i as f32 / segments as f32 * std::f32::consts::TAU;
+            center + Vector2::new(angle.cos() * radii.x, angle.sin() * radii.y)
+        }).collect()
+    }
+
+    pub fn fill_rect(&mut self, rect: Rect, color: Color) {
+        self.push_quad(Self::rect_corners(rect), color);
+    }
+
+    pub fn stroke_rect(&mut self, rect: Rect, thickness: f32, color: Color) {
+        self.stroke_polygon(&Self::rect_corners(rect), thickness, color);
+    }
+
+    pub fn fill_circle(&mut self, center: Vector2<f32>, radius: f32, segments: u32, color: Color) {
+        self.fill_ellipse(center, Vector2::new(radius, radius), segments, color);
+    }
+
+    pub fn stroke_circle(&mut self, center: Vector2<f32>, radius: f32, segments: u32, thickness: f32, color: Color) {
+        self.stroke_ellipse(center, Vector2::new(radius, radius), segments, thickness, color);
+    }
+
+    pub fn fill_ellipse(&mut self, center: Vector2<f32>, radii: Vector2<f32>, segments: u32, color: Color) {
+        self.fill_polygon(&Self::ellipse_points(center, radii, segments), color);
+    }
+
+    pub fn stroke_ellipse(&mut self, center: Vector2<f32>, radii: Vector2<f32>, segments: u32, thickness: f32, color: Color) {
+        self.stroke_polygon(&Self::ellipse_points(center, radii, segments), thickness, color);
+    }
+
+    // Triangulated as a fan, so the points have to describe a convex polygon.
+    pub fn fill_polygon(&mut self, points: &[Vector2<f32>], color: Color) {
+        if points.len() < 3 {
+            return;
+        }
+        let first = self.push_vertex(points[0], color);
+        for point in &points[1..] {
+            self.push_vertex(*point, color);
+        }
+        for i in 1..points.len() as u32 - 1 {
+            self.push_indices(&[first, first + i, first + i + 1]);
+        }
+    }
+
+    // Outline of a closed polygon, centred on its edges with mitred corners.
+    pub fn stroke_polygon(&mut self, points: &[Vector2<f32>], thickness: f32, color: Color) {
+        let count = points.len();
+        if count < 2 {
+            return;
+        }
+        let half = thickness / 2.0;
+        let first = self.vertices.len() as u32;
+        for i in 0..count {
+            let previous = points[(i + count - 1) % count];
+            let point = points[i];
+            let next = points[(i + 1) % count];
+            let offset = Self::miter(previous, point, next) * half;
+            self.push_vertex(point + offset, color);
+            self.push_vertex(point - offset, color);
+        }
+        for i in 0..count as u32 {
+            let current = first + i * 2;
+            let next = first + ((i + 1) % count as u32) * 2;
+            self.push_indices(&[current, current + 1, next + 1, current, next + 1, next]);
+        }
+    }
+
+    pub fn line(&mut self, from: Vector2<f32>, to: Vector2<f32>, thickness: f32, color: Color) {
+        let direction = to - from;
+        if direction.magnitude2() <= f32::EPSILON {
+            return;
+        }
+        let normal = Self::normal(direction) * (thickness / 2.0);
+        self.push_quad([from + normal, from - normal, to - normal, to + normal], color);
+    }
+
+    // Open chain of lines with mitred joints.
+    pub fn polyline(&mut self, points: &[Vector2<f32>], thickness: f32, color: Color) {
+        let count = points.len();
+        if count < 2 {
+            return;
+        }
+        let half = thickness / 2.0;
+        let first = self.vertices.len() as u32;
+        for i in 0..count {
+            let point = points[i];
+            let direction = if i == 0 {
+                Self::normal(points[1] - point)
+            }
+            else if i == count - 1 {
+                Self::normal(point - points[i - 1])
+            }
+            else {
+                Self::miter(points[i - 1], point, points[i + 1])
+            };
+            let offset = direction * half;
+            self.push_vertex(point + offset, color);
+            self.push_vertex(point - offset, color);
+        }
+        for i in 0..count as u32 - 1 {
+            let current = first + i * 2;
+            let next = current + 2;
+            self.push_indices(&[current, current + 1, next + 1, current, next + 1, next]);
+        }
+    }
+
+    // Left hand normal of `direction`, zero for a zero length direction.
+    fn normal(direction: Vector2<f32>) -> Vector2<f32> {
+        let length = direction.magnitude();
+        if length <= f32::EPSILON {
+            return Vector2::new(0.0, 0.0);
+        }
+        Vector2::new(-direction.y, direction.x) / length
+    }
+
+    // Offset of the corner at `point` for a line of half thickness 1.0.
+    fn miter(previous: Vector2<f32>, point: Vector2<f32>, next: Vector2<f32>) -> Vector2<f32> {
+        let incoming = Self::normal(point - previous);
+        let outgoing = Self::normal(next - point);
+        let sum = incoming + outgoing;
+        if sum.magnitude2() <= f32::EPSILON {
+            return incoming;
+        }
+        let direction = sum.normalize();
+        let scale = 1.0 / direction.dot(incoming).max(1.0 / MITER_LIMIT);
+        direction * scale
+    }
+}
@@ -0,0 +1,50 @@
+struct InstanceInput {
+    [[location(3)]] model_matrix_0: vec4<f32>;
+    [[location(4)]] model_matrix_1: vec4<f32>;
+    [[location(5)]] model_matrix_2: vec4<f32>;
+    [[location(6)]] model_matrix_3: vec4<f32>;
+    [[location(7)]] uv_rect: vec4<f32>;
+    [[location(8)]] tint: vec4<f32>;
+};
+
+struct VertexInput {
+    [[location(0)]] position: vec3<f32>;
+    [[location(1)]] vertex_color: vec4<f32>;
+    [[location(2)]] tex_coords: vec2<f32>;
+};
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] vertex_color: vec4<f32>;
+    [[location(1)]] tex_coords: vec2<f32>;
+};
+
+struct CameraUniform {
+    view_projection: mat4x4<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> camera: CameraUniform;
+
+[[group(1), binding(0)]]
+var t_diffuse: texture_2d<f32>;
+[[group(1), binding(1)]]
+var s_diffuse: sampler;
+
+[[stage(vertex)]]
+fn vertex_main(
+    model: VertexInput,
+    instance: InstanceInput
+) -> VertexOutput {
+    let model_matrix = mat4x4<f32>(
+        instance.model_matrix_0,
+        instance.model_matrix_1,
+        instance.model_matrix_2,
+        instance.model_matrix_3,
+    );
+    var out: VertexOutput;
+    out.clip_position = camera.view_projection * model_matrix * vec4<f32>(model.position.x, model.position.y, 0.0, 1.0);
+    out.vertex_color = model.vertex_color * instance.tint;
+    out.tex_coords = instance.uv_rect.xy + model.tex_coords * instance.uv_rect.zw;
+    return out;
+}
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+
+use cgmath::{Matrix4, Vector2, Vector3};
+use wgpu::Queue;
+
+use super::{RenderableInstanceRaw, batch::RenderLayer, texture::{Texture, TextureHandle}};
+use crate::error::{RueError, RueResult};
+use crate::helpers::colors::Color;
+
+// Glyphs of every size a font is drawn at share one atlas of this many pixels square.
+pub const ATLAS_SIZE: u32 = 1024;
+// Empty pixels around every glyph so neighbours never bleed into each other.
+const ATLAS_PADDING: u32 = 1;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FontHandle(pub(crate) usize);
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct TextStyle {
+    // Pixels per em, which at camera zoom 1.0 is also world units.
+    pub size: f32,
+    pub color: Color,
+    // Multiplier on the line height the font asks for.
+    pub line_height: f32,
+    pub align: TextAlign,
+    // Lines longer than this are wrapped at spaces, words longer than it are left whole.
+    pub max_width: Option<f32>,
+    pub layer: RenderLayer,
+    pub z: f32,
+}
+
+impl TextStyle {
+    pub fn new(size: f32) -> Self {
+        TextStyle {
+            size,
+            color: Color::rgb(1.0, 1.0, 1.0),
+            line_height: 1.0,
+            align: TextAlign::default(),
+            max_width: None,
+            layer: RenderLayer::UI,
+            z: 0.0,
+        }
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_line_height(mut self, line_height: f32) -> Self {
+        self.line_height = line_height;
+        self
+    }
+
+    pub fn with_align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn with_max_width(mut self, max_width: f32) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    pub fn with_layer(mut self, layer: RenderLayer) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    pub fn with_z(mut self, z: f32) -> Self {
+        self.z = z;
+        self
+    }
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        TextStyle::new(16.0)
+    }
+}
+
+// A character placed on its baseline, relative to the top left corner of the text.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PositionedGlyph {
+    pub character: char,
+    pub position: Vector2<f32>,
+}
+
+#[derive(Clone, Debug)]
+pub struct TextLayout {
+    pub glyphs: Vec<PositionedGlyph>,
+    // Width and height of the laid out block, y grows downwards from the top left corner.
+    pub size: Vector2<f32>,
+    pub line_count: usize,
+}
+
+// Where a rasterised glyph sits in the atlas and how it is placed relative to the pen.
+#[derive(Copy, Clone, Debug)]
+struct AtlasGlyph {
+    uv_rect: [f32; 4],
+    size: Vector2<f32>,
+    // Bottom left corner of the bitmap relative to the pen on the baseline.
+    offset: Vector2<f32>,
+}
+
+pub(crate) struct Font {
+    font: fontdue::Font,
+    pub atlas: TextureHandle,
+    // Keyed by character and the bits of the pixel size, None for glyphs without pixels such as spaces.
+    glyphs: HashMap<(char, u32), Option<AtlasGlyph>>,
+    // Shelf packing: glyphs fill rows left to right, a new row starts below the tallest glyph of the last one.
+    cursor: Vector2<u32>,
+    row_height: u32,
+}
+
+impl Font {
+    pub fn from_bytes(bytes: &[u8], label: &str, atlas: TextureHandle) -> RueResult<Self> {
+        let font = fontdue::Font::from_bytes(bytes, fontdue::FontSettings::default()).map_err(|e| RueError::Asset {
+            path: label.to_string(),
+            message: e.to_string(),
+        })?;
+        Ok(Font {
+            font,
+            atlas,
+            glyphs: HashMap::new(),
+            cursor: Vector2::new(ATLAS_PADDING, ATLAS_PADDING),
+            row_height: 0,
+        })
+    }
+
+    fn line_metrics(&self, size: f32) -> fontdue::LineMetrics {
+        self.font.horizontal_line_metrics(size).unwrap_or(fontdue::LineMetrics {
+            ascent: size,
+            descent: 0.0,
+            line_gap: size * 0.2,
+            new_line_size: size * 1.2,
+        })
+    }
+
+    fn line_width(&self, line: &str, size: f32) -> f32 {
+        let mut width = 0.0;
+        let mut previous = None;
+        for character in line.chars() {
+            if let Some(previous) = previous {
+                width += self.font.horizontal_kern(previous, character, size).unwrap_or(0.0);
+            }
+            width += self.font.metrics(character, size).advance_width;
+            previous = Some(character);
+        }
+        width
+    }
+
+    fn wrap(&self, text: &str, style: &TextStyle) -> Vec<String> {
+        let mut lines = Vec::new();
+        for paragraph in text.split('\n') {
+            let mut line = String::new();
+            for word in paragraph.split(' ') {
+                let candidate = if line.is_empty() { word.to_string() } else { format!("{} {}", line, word) };
+                match style.max_width {
+                    Some(max_width) if !line.is_empty() && self.line_width(&candidate, style.size) > max_width => {
+                        lines.push(std::mem::replace(&mut line, word.to_string()));
+                    },
+                    _ => line = candidate,
+                }
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
+    pub fn layout(&self, text: &str, style: &TextStyle) -> TextLayout {
+        let metrics = self.line_metrics(style.size);
+        let line_advance = metrics.new_line_size * style.line_height;
+        let lines = self.wrap(text, style);
+        let widths: Vec<f32> = lines.iter().map(|line| self.line_width(line, style.size)).collect();
+        let block_width = style.max_width.unwrap_or_else(|| widths.iter().cloned().fold(0.0, f32::max));
+
+        let mut glyphs = Vec::new();
+        for (i, (line, width)) in lines.iter().zip(&widths).enumerate() {
+            let mut x = match style.align {
+                TextAlign::Left => 0.0,
+                TextAlign::Center => (block_width - width) / 2.0,
+                TextAlign::Right => block_width - width,
+            };
+            let baseline = metrics.ascent + i as f32 * line_advance;
+            let mut previous = None;
+            for character in line.chars() {
+                if let Some(previous) = previous {
+                    x += self.font.horizontal_kern(previous, character, style.size).unwrap_or(0.0);
+                }
+                glyphs.push(PositionedGlyph {
+                    character,
+                    position: Vector2::new(x, baseline),
+                });
+                x += self.font.metrics(character, style.size).advance_width;
+                previous = Some(character);
+            }
+        }
+
+        TextLayout {
+            glyphs,
+            size: Vector2::new(block_width, (lines.len() - 1) as f32 * line_advance + metrics.ascent - metrics.descent),
+            line_count: lines.len(),
+        }
+    }
+
+    // Rasterises the glyph into the atlas the first time it is asked for.
+    fn glyph(&mut self, atlas: &Texture, queue: &Queue, character: char, size: f32) -> Option<AtlasGlyph> {
+        let key = (character, size.to_bits());
+        if let Some(glyph) = self.glyphs.get(&key) {
+            return *glyph;
+        }
+        let (metrics, coverage) = self.font.rasterize(character, size);
+        let (width, height) = (metrics.width as u32, metrics.height as u32);
+        if width == 0 || height == 0 {
+            self.glyphs.insert(key, None);
+            return None;
+        }
+        if self.cursor.x + width + ATLAS_PADDING > ATLAS_SIZE {
+            self.cursor = Vector2::new(ATLAS_PADDING, self.cursor.y + self.row_height + ATLAS_PADDING);
+            self.row_height = 0;
+        }
+        if self.cursor.y + height + ATLAS_PADDING > ATLAS_SIZE {
+            log::warn!("Glyph atlas is full, '{}' at {}px will not be drawn.", character, size);
+            self.glyphs.insert(key, None);
+            return None;
+        }
+
+        // White pixels with the coverage as alpha, so the tint alone decides the colour.
+        let pixels: Vec<u8> = coverage.iter().flat_map(|&alpha| [255, 255, 255, alpha]).collect();
+        atlas.write_region(queue, self.cursor.x, self.cursor.y, width, height, &pixels);
+        let atlas_size = ATLAS_SIZE as f32;
+        let glyph = AtlasGlyph {
+            uv_rect: [
+                self.cursor.x as f32 / atlas_size,
+                self.cursor.y as f32 / atlas_size,
+                width as f32 / atlas_size,
+                height as f32 / atlas_size,
+            ],
+            size: Vector2::new(width as f32, height as f32),
+            offset: Vector2::new(metrics.xmin as f32, metrics.ymin as f32),
+        };
+        self.cursor.x += width + ATLAS_PADDING;
+        self.row_height = self.row_height.max(height);
+        self.glyphs.insert(key, Some(glyph));
+        Some(glyph)
+    }
+
+    // Instances for every visible glyph of `layout`, with `position` the top left corner of the text in world space.
+    pub fn glyph_instances(&mut self, atlas: &Texture, queue: &Queue, layout: &TextLayout, position: Vector2<f32>, style: &TextStyle) -> Vec<RenderableInstanceRaw> {
+        let tint = [style.color.r as f32, style.color.g as f32, style.color.b as f32, style.color.a as f32];
+        layout.glyphs.iter().filter_map(|positioned| {
+            let glyph = self.glyph(atlas, queue, positioned.character, style.size)?;
+            // Layout y grows downwards while world y grows upwards. Snapping to whole units keeps glyphs sharp at zoom 1.0.
+            let pen = Vector2::new(position.x + positioned.position.x, position.y - positioned.position.y);
+            let bottom_left = Vector2::new((pen.x + glyph.offset.x).round(), (pen.y + glyph.offset.y).round());
+            let center = bottom_left + glyph.size / 2.0;
+            Some(RenderableInstanceRaw {
+                model: (Matrix4::from_translation(Vector3::new(center.x, center.y, 0.0))
+                    * Matrix4::from_nonuniform_scale(glyph.size.x, glyph.size.y, 1.0)).into(),
+                uv_rect: glyph.uv_rect,
+                tint,
+            })
+        }).collect()
+    }
+}
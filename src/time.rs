@@ -0,0 +1,210 @@
+use std::{
+    cell::{Cell, RefCell}, rc::Rc, time::{Duration, Instant},
+};
+
+// Shared view of game time for scenes, actors and hooks. Scaled values stop while paused, unscaled ones never do.
+#[derive(Debug, Clone)]
+pub struct Time {
+    time_scale: f32,
+    paused: bool,
+    delta: f32,
+    unscaled_delta: f32,
+    fixed_delta: f32,
+    elapsed: f64,
+    unscaled_elapsed: f64,
+    fixed_ticks: u64,
+    frames: u64,
+}
+
+pub type TimeHandle = Rc<RefCell<Time>>;
+
+impl Time {
+    pub fn new(fixed_delta: f32) -> Self {
+        Time {
+            time_scale: 1.0,
+            paused: false,
+            delta: 0.0,
+            unscaled_delta: 0.0,
+            fixed_delta,
+            elapsed: 0.0,
+            unscaled_elapsed: 0.0,
+            fixed_ticks: 0,
+            frames: 0,
+        }
+    }
+
+    // Scaled length of the last frame, 0 while paused.
+    pub fn delta(&self) -> f32 {
+        self.delta
+    }
+
+    // Real length of the last frame, for UI and anything else that ignores pausing and time scale.
+    pub fn unscaled_delta(&self) -> f32 {
+        self.unscaled_delta
+    }
+
+    pub fn fixed_delta(&self) -> f32 {
+        self.fixed_delta
+    }
+
+    pub fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+
+    pub fn unscaled_elapsed(&self) -> f64 {
+        self.unscaled_elapsed
+    }
+
+    pub fn fixed_ticks(&self) -> u64 {
+        self.fixed_ticks
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale.max(0.0);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub(crate) fn set_fixed_delta(&mut self, fixed_delta: f32) {
+        self.fixed_delta = fixed_delta;
+    }
+
+    // Records a frame of `frame_time` real seconds of which `simulated_time` reaches the simulation, and returns
+    // that part scaled. Unscaled values keep the whole frame even when a hitch was clamped.
+    pub(crate) fn begin_frame(&mut self, frame_time: f32, simulated_time: f32) -> f32 {
+        self.frames += 1;
+        self.unscaled_delta = frame_time;
+        self.unscaled_elapsed += frame_time as f64;
+        self.delta = if self.paused { 0.0 } else { simulated_time * self.time_scale };
+        self.elapsed += self.delta as f64;
+        self.delta
+    }
+
+    pub(crate) fn count_fixed_tick(&mut self) {
+        self.fixed_ticks += 1;
+    }
+}
+
+// Source of frame timestamps, swapped out for a ManualClock to drive the game deterministically.
+pub trait Clock {
+    // Time elapsed since an arbitrary but fixed origin.
+    fn now(&self) -> Duration;
+}
+
+pub struct SystemClock {
+    origin: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        SystemClock {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+// Only moves when told to. Clones share the same time, so keep one around to advance a clock given to a Game.
+#[derive(Clone, Default)]
+pub struct ManualClock {
+    now: Rc<Cell<Duration>>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        ManualClock::default()
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.now.set(self.now.get() + by);
+    }
+
+    pub fn set(&self, now: Duration) {
+        self.now.set(now);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FramePacing {
+    // Render as often as the event loop allows.
+    Uncapped,
+    // Cap rendering to the given frames per second using a sleep/spin limiter.
+    Capped(u32),
+    // Let the surface's present mode pace frames to the display refresh rate.
+    VSync,
+}
+
+pub struct FrameLimiter {
+    frame_duration: Duration,
+    next_frame: Instant,
+    // Sleeping is imprecise, so the last stretch before a deadline is spun instead.
+    pub spin_threshold: Duration,
+}
+
+impl FrameLimiter {
+    pub fn new(fps: u32) -> Self {
+        FrameLimiter {
+            frame_duration: Duration::from_secs_f64(1.0 / (fps.max(1) as f64)),
+            next_frame: Instant::now(),
+            spin_threshold: Duration::from_millis(2),
+        }
+    }
+
+    pub fn frame_duration(&self) -> Duration {
+        self.frame_duration
+    }
+
+    // Blocks until the next frame deadline.
+    pub fn wait(&mut self) {
+        self.next_frame += self.frame_duration;
+        let now = Instant::now();
+        if now >= self.next_frame {
+            // We are behind schedule, so start counting again from now instead of rushing to catch up.
+            self.next_frame = now;
+            return;
+        }
+
+        let remaining = self.next_frame - now;
+        if remaining > self.spin_threshold {
+            std::thread::sleep(remaining - self.spin_threshold);
+        }
+        while Instant::now() < self.next_frame {
+            std::hint::spin_loop();
+        }
+    }
+}